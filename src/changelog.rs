@@ -0,0 +1,322 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use git2::{Oid, Repository};
+
+use crate::{
+    conventional::ConventionalCommit, git, settings::ProjectSettings, staging::Staging,
+    version::Version,
+};
+
+const HEADER: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/).
+";
+
+/// Changelog sections in the order they are rendered, with the commit types
+/// that belong to them. Commits of other types go to the "Other" section.
+const GROUPS: [(&str, &[&str]); 3] = [
+    ("Added", &["feat"]),
+    ("Fixed", &["fix"]),
+    ("Changed", &["perf", "refactor"]),
+];
+
+/// Generate the section for a release and stage it at the top of the changelog
+///
+/// The section lists the commits that touched the project path since the
+/// previous release commit, or all of them for the first release. Returns the path of the changelog file, or None
+/// if the project has no changelog configured.
+pub fn update(
+    repo: &Repository,
+    settings: &ProjectSettings,
+    version: &Version,
+    previous_release: Option<Oid>,
+    staging: &mut Staging,
+) -> Result<Option<PathBuf>> {
+    let Some(changelog_path) = &settings.changelog else {
+        return Ok(None);
+    };
+    let head_id = repo.head()?.peel_to_commit()?.id();
+    let commits =
+        git::commits_touching_path(repo, &settings.project_path, previous_release, head_id)?;
+    let commits = settings.ignore_rules()?.filter(commits)?;
+    let date = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    let messages = commits
+        .iter()
+        .map(|commit| commit.message().unwrap_or_default())
+        .collect::<Vec<_>>();
+    let section = render_section(version, &date, &messages);
+    let content = match staging.read(changelog_path) {
+        Ok(content) => content,
+        Err(e)
+            if e.downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound) =>
+        {
+            HEADER.to_string()
+        }
+        Err(e) => return Err(e),
+    };
+    staging.write(changelog_path, insert_section(&content, &section));
+    Ok(Some(changelog_path.clone()))
+}
+
+/// Render a Keep a Changelog section from commit messages grouped by type
+fn render_section(version: &Version, date: &str, messages: &[&str]) -> String {
+    let changes = render_changes(messages);
+    match changes.is_empty() {
+        true => format!("## [{}] - {}\n", version, date),
+        false => format!("## [{}] - {}\n\n{}", version, date, changes),
+    }
+}
+
+/// Render commit messages as Markdown lists grouped by commit type
+///
+/// Each non-empty group gets a third level heading. Returns an empty
+/// string if there are no messages.
+pub fn render_changes(messages: &[&str]) -> String {
+    let mut groups: Vec<(&str, Vec<String>)> = GROUPS
+        .iter()
+        .map(|(title, _)| (*title, Vec::new()))
+        .chain(std::iter::once(("Other", Vec::new())))
+        .collect();
+    for message in messages {
+        let (group, entry) = match ConventionalCommit::parse(message) {
+            Some(conventional) => {
+                let group = GROUPS
+                    .iter()
+                    .position(|(_, types)| types.contains(&conventional.commit_type.as_str()))
+                    .unwrap_or(GROUPS.len());
+                (group, format_entry(&conventional))
+            }
+            None => (
+                GROUPS.len(),
+                message
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+            ),
+        };
+        groups[group].1.push(format!("- {}", entry));
+    }
+    groups
+        .iter()
+        .filter(|(_, entries)| !entries.is_empty())
+        .map(|(title, entries)| format!("### {}\n\n{}\n", title, entries.join("\n")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_entry(commit: &ConventionalCommit) -> String {
+    let mut entry = String::new();
+    if commit.breaking {
+        entry.push_str("**BREAKING** ");
+    }
+    if let Some(scope) = &commit.scope {
+        entry.push_str(&format!("*({})* ", scope));
+    }
+    entry.push_str(&commit.description);
+    entry
+}
+
+/// Insert a section before the latest release, keeping an Unreleased section on top
+fn insert_section(changelog: &str, section: &str) -> String {
+    let position = changelog
+        .match_indices("## ")
+        .filter(|(index, _)| *index == 0 || changelog[..*index].ends_with('\n'))
+        .map(|(index, _)| index)
+        .find(|index| {
+            !changelog[*index..]
+                .to_lowercase()
+                .starts_with("## [unreleased]")
+        });
+    match position {
+        Some(index) => format!(
+            "{}{}\n{}",
+            &changelog[..index],
+            section,
+            &changelog[index..]
+        ),
+        None => format!("{}\n\n{}", changelog.trim_end(), section),
+    }
+}
+
+/// Extract the section for a version from a Keep a Changelog formatted file
+///
+/// The section starts at the `## [<version>]` (or `## <version>`) heading and
+/// ends before the next second level heading. The heading itself is not
+/// included. Returns None if the version has no section or it is empty.
+pub fn excerpt(changelog: &str, version: &Version) -> Option<String> {
+    let version = version.to_string();
+    let mut lines = changelog
+        .lines()
+        .skip_while(|line| !is_heading_for(line, &version));
+    lines.next()?;
+    let section = lines
+        .take_while(|line| !line.starts_with("## "))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let section = section.trim();
+    (!section.is_empty()).then(|| section.to_string())
+}
+
+fn is_heading_for(line: &str, version: &str) -> bool {
+    let Some(heading) = line.strip_prefix("## ") else {
+        return false;
+    };
+    heading
+        .trim_start_matches('[')
+        .strip_prefix(version)
+        .map(|rest| !rest.starts_with(|c: char| c.is_ascii_digit() || c == '.'))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{commit_all, init_repo, initial_commit, write};
+    use crate::version::ToVersion;
+
+    const CHANGELOG: &str = r#"# Changelog
+
+## [Unreleased]
+
+## [25.3.10] - 2025-03-20
+
+### Fixed
+- later fix
+
+## [25.3.1](https://example.com/compare) - 2025-03-02
+
+### Added
+- new feature
+
+## 25.3.0
+"#;
+
+    #[test]
+    fn test_excerpt() {
+        assert_eq!(
+            excerpt(CHANGELOG, &"25.3.1".to_version()).unwrap(),
+            "### Added\n- new feature"
+        );
+        assert_eq!(
+            excerpt(CHANGELOG, &"25.3.10".to_version()).unwrap(),
+            "### Fixed\n- later fix"
+        );
+    }
+
+    #[test]
+    fn test_insert_section() {
+        let section = "## [25.4.0] - 2025-04-01\n\n### Added\n\n- thing\n";
+        let updated = insert_section(CHANGELOG, section);
+        assert!(updated.contains(
+            "## [Unreleased]\n\n## [25.4.0] - 2025-04-01\n\n### Added\n\n- thing\n\n## [25.3.10]"
+        ));
+        assert_eq!(
+            excerpt(&updated, &"25.4.0".to_version()).unwrap(),
+            "### Added\n\n- thing"
+        );
+    }
+
+    #[test]
+    fn test_insert_section_new_file() {
+        let section = "## [25.4.0] - 2025-04-01\n";
+        assert_eq!(
+            insert_section(HEADER, section),
+            format!("{}\n{}", HEADER, section)
+        );
+    }
+
+    #[test]
+    fn test_render_section() {
+        let messages = [
+            "fix(api): handle empty body",
+            "Update dependencies\n\nSome details",
+            "feat: add endpoint",
+            "docs: describe endpoint",
+            "feat!: drop v1",
+        ];
+        assert_eq!(
+            render_section(&"25.4.0".to_version(), "2025-04-01", &messages),
+            r#"## [25.4.0] - 2025-04-01
+
+### Added
+
+- add endpoint
+- **BREAKING** drop v1
+
+### Fixed
+
+- *(api)* handle empty body
+
+### Other
+
+- Update dependencies
+- describe endpoint
+"#
+        );
+    }
+
+    #[test]
+    fn test_format_entry() {
+        let commit = ConventionalCommit::parse("feat(api)!: remove v1").unwrap();
+        assert_eq!(format_entry(&commit), "**BREAKING** *(api)* remove v1");
+        let commit = ConventionalCommit::parse("fix: typo").unwrap();
+        assert_eq!(format_entry(&commit), "typo");
+    }
+
+    #[test]
+    fn test_update_first_release() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "README.md", "readme");
+        initial_commit(&repo, &["README.md"]);
+        write(dir.path(), "app/main.rs", "fn main() {}");
+        commit_all(&repo, "feat: add main");
+        write(dir.path(), "app/lib.rs", "");
+        commit_all(&repo, "fix: add lib [skip release]");
+
+        let mut settings: ProjectSettings =
+            serde_yaml::from_str("type: versionfile\npath: app\nchangelog: app/CHANGELOG.md")
+                .unwrap();
+        settings.ignore = serde_yaml::from_str(r"[{message: '\[skip release\]'}]").unwrap();
+        let mut staging = Staging::new(dir.path());
+        update(&repo, &settings, &"25.1.0".to_version(), None, &mut staging).unwrap();
+        let changelog = staging.staged("app/CHANGELOG.md").unwrap();
+        assert_eq!(
+            excerpt(changelog, &"25.1.0".to_version()).unwrap(),
+            "### Added\n\n- add main"
+        );
+    }
+
+    #[test]
+    fn test_update_unreadable() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "25.1.0\n");
+        initial_commit(&repo, &["app/VERSION"]);
+        let settings: ProjectSettings =
+            serde_yaml::from_str("type: versionfile\npath: app\nchangelog: app/CHANGELOG.md")
+                .unwrap();
+        let version = "25.1.1".to_version();
+        let mut staging = Staging::new(dir.path());
+        update(&repo, &settings, &version, None, &mut staging).unwrap();
+        assert!(staging
+            .staged("app/CHANGELOG.md")
+            .unwrap()
+            .starts_with(HEADER));
+
+        std::fs::write(dir.path().join("app/CHANGELOG.md"), b"# Changelog\n\xff\n").unwrap();
+        let mut staging = Staging::new(dir.path());
+        assert!(update(&repo, &settings, &version, None, &mut staging).is_err());
+        assert!(staging.staged("app/CHANGELOG.md").is_none());
+    }
+
+    #[test]
+    fn test_excerpt_missing() {
+        assert!(excerpt(CHANGELOG, &"25.3.0".to_version()).is_none());
+        assert!(excerpt(CHANGELOG, &"25.4.0".to_version()).is_none());
+    }
+}
@@ -1,18 +1,24 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Args, Parser, ValueEnum};
 use clap_verbosity_flag::{Verbosity, WarnLevel};
 
+use monoverse::{init::ConfigFormat, projects::ReleaseOptions, version::Version};
+
 #[derive(Parser)]
 #[clap(name = env!("CARGO_PKG_NAME"), version = env!("CARGO_PKG_VERSION"))]
 pub struct Opts {
     #[clap(subcommand)]
     pub subcmd: SubCommand,
 
-    /// Repository path
-    #[clap(long, default_value = ".", global = true)]
+    /// Repository path, defaults to the repository containing the current directory
+    #[clap(long, global = true)]
     pub repo_path: Option<PathBuf>,
 
+    /// Configuration file, defaults to monoverse.{yaml,json,toml} at the repository root
+    #[clap(long, global = true)]
+    pub config: Option<PathBuf>,
+
     #[clap(flatten)]
     pub verbosity: Verbosity<WarnLevel>,
 }
@@ -21,8 +27,20 @@ pub struct Opts {
 pub enum SubCommand {
     /// Release a project
     Release(Release),
+    /// Set the version of a project
+    Set(Set),
     /// Print the next version for a project
     Next(Next),
+    /// Print release notes for a project
+    Notes(Notes),
+    /// Undo the last release of a project or a group
+    Rollback(Rollback),
+    /// Create a configuration file by scanning the repository for projects
+    Init(Init),
+    /// Check the configuration against the repository
+    Validate,
+    /// Print the JSON Schema of the configuration file
+    Schema,
 }
 
 #[derive(Parser)]
@@ -32,19 +50,116 @@ pub struct Release {
     /// Force release
     #[clap(long, short)]
     pub force: bool,
+    #[clap(flatten)]
+    pub update: UpdateArgs,
+}
+
+#[derive(Parser)]
+pub struct Set {
+    /// Project name
+    pub project: String,
+    /// The new version
+    pub version: Version,
+    #[clap(flatten)]
+    pub update: UpdateArgs,
+}
+
+/// Options shared by the commands that write a new version
+#[derive(Args)]
+pub struct UpdateArgs {
     /// Commit changes
     #[clap(long)]
     pub commit: bool,
     /// Create a tag
     #[clap(long, requires = "commit")]
     pub tag: bool,
+    /// Push the branch and the created tag to the remote
+    #[clap(long, requires = "commit")]
+    pub push: bool,
+    /// Sign the commit and tag even if `commit.gpgsign` or `tag.gpgsign` is not set
+    #[clap(long, requires = "commit")]
+    pub sign: bool,
+    /// Release even if the project has uncommitted changes
+    #[clap(long)]
+    pub allow_dirty: bool,
     /// Run `helm dependency update` for helm dependents
     #[clap(long)]
     pub helm_dependency_update: bool,
 }
 
+impl UpdateArgs {
+    /// Return the release options for the flags
+    pub fn options(&self, force: bool) -> ReleaseOptions {
+        ReleaseOptions {
+            force,
+            allow_dirty: self.allow_dirty,
+            commit: self.commit,
+            tag: self.tag,
+            push: self.push,
+            sign: self.sign,
+            helm_dependency_update: self.helm_dependency_update,
+        }
+    }
+}
+
 #[derive(Parser)]
 pub struct Next {
     /// Project name
     pub project: String,
 }
+
+#[derive(Parser)]
+pub struct Notes {
+    /// Project name
+    pub project: String,
+    /// Start of the range as a version or git revision, defaults to the previous release
+    #[clap(long)]
+    pub from: Option<String>,
+    /// End of the range as a version or git revision, defaults to HEAD
+    #[clap(long)]
+    pub to: Option<String>,
+    /// Template file for the release notes
+    #[clap(long)]
+    pub template: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+pub struct Rollback {
+    /// Project or group name
+    pub project: String,
+    /// Create a revert commit instead of resetting the branch
+    #[clap(long)]
+    pub revert: bool,
+}
+
+#[derive(Parser)]
+pub struct Init {
+    /// Format of the configuration file
+    #[clap(long, value_enum, default_value_t)]
+    pub format: Format,
+    /// Print the configuration instead of writing it
+    #[clap(long)]
+    pub dry_run: bool,
+    /// Overwrite an existing configuration file
+    #[clap(long)]
+    pub force: bool,
+}
+
+/// Format of the configuration file written by `init`
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum Format {
+    #[default]
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl From<Format> for ConfigFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Yaml => ConfigFormat::Yaml,
+            Format::Toml => ConfigFormat::Toml,
+            Format::Json => ConfigFormat::Json,
+        }
+    }
+}
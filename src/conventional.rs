@@ -0,0 +1,139 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::version::Bump;
+
+/// Commit types that do not trigger a release on their own
+const NON_RELEASING_TYPES: [&str; 2] = ["chore", "docs"];
+
+/// A commit message following the Conventional Commits specification
+///
+/// See <https://www.conventionalcommits.org/en/v1.0.0/>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConventionalCommit {
+    pub commit_type: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub description: String,
+}
+
+impl ConventionalCommit {
+    /// Parse a commit message, returning None if it is not a conventional commit
+    ///
+    /// A commit is breaking if the type is followed by `!` or the message has
+    /// a `BREAKING CHANGE` footer.
+    pub fn parse(message: &str) -> Option<Self> {
+        static PATTERN: OnceLock<Regex> = OnceLock::new();
+        let pattern = PATTERN.get_or_init(|| {
+            Regex::new(
+                r"^(?P<type>[[:alpha:]]+)(?:\((?P<scope>[^()]*)\))?(?P<breaking>!)?:\s+(?P<description>.+)$",
+            )
+            .unwrap()
+        });
+        let summary = message.lines().next()?.trim();
+        let captures = pattern.captures(summary)?;
+        let breaking_footer = message.lines().skip(1).any(|line| {
+            line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+        });
+        Some(Self {
+            commit_type: captures["type"].to_lowercase(),
+            scope: captures
+                .name("scope")
+                .map(|scope| scope.as_str().trim().to_string())
+                .filter(|scope| !scope.is_empty()),
+            breaking: captures.name("breaking").is_some() || breaking_footer,
+            description: captures["description"].trim().to_string(),
+        })
+    }
+
+    /// Return the bump level for this commit
+    ///
+    /// Breaking changes bump the major version, features the minor version,
+    /// chores and documentation nothing, and everything else the patch
+    /// version.
+    pub fn bump(&self) -> Option<Bump> {
+        if self.breaking {
+            Some(Bump::Major)
+        } else if self.commit_type == "feat" {
+            Some(Bump::Minor)
+        } else if NON_RELEASING_TYPES.contains(&self.commit_type.as_str()) {
+            None
+        } else {
+            Some(Bump::Patch)
+        }
+    }
+}
+
+/// Return the largest bump level for a list of commit messages
+///
+/// Commits that do not follow the Conventional Commits format bump the patch
+/// version. Returns None if no commit requires a release.
+pub fn bump_for(messages: &[&str]) -> Option<Bump> {
+    messages
+        .iter()
+        .filter_map(|message| match ConventionalCommit::parse(message) {
+            Some(commit) => commit.bump(),
+            None => Some(Bump::Patch),
+        })
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let commit = ConventionalCommit::parse("feat(api): add endpoint\n\nBody").unwrap();
+        assert_eq!(commit.commit_type, "feat");
+        assert_eq!(commit.scope.as_deref(), Some("api"));
+        assert!(!commit.breaking);
+        assert_eq!(commit.description, "add endpoint");
+    }
+
+    #[test]
+    fn test_parse_breaking() {
+        assert!(
+            ConventionalCommit::parse("fix!: drop support")
+                .unwrap()
+                .breaking
+        );
+        assert!(
+            ConventionalCommit::parse("refactor(db)!: rename table")
+                .unwrap()
+                .breaking
+        );
+        let commit =
+            ConventionalCommit::parse("feat: new config\n\nBREAKING CHANGE: old keys removed")
+                .unwrap();
+        assert!(commit.breaking);
+        assert_eq!(commit.scope, None);
+    }
+
+    #[test]
+    fn test_bump_for() {
+        assert_eq!(
+            bump_for(&["fix: a", "feat(api): b", "docs: c"]),
+            Some(Bump::Minor)
+        );
+        assert_eq!(
+            bump_for(&["fix: a", "chore!: drop node 16"]),
+            Some(Bump::Major)
+        );
+        assert_eq!(
+            bump_for(&["fix: a", "feat: b\n\nBREAKING CHANGE: c"]),
+            Some(Bump::Major)
+        );
+        assert_eq!(bump_for(&["Update README", "docs: c"]), Some(Bump::Patch));
+        assert_eq!(bump_for(&["chore: deps", "docs: c"]), None);
+        assert_eq!(bump_for(&[]), None);
+    }
+
+    #[test]
+    fn test_parse_not_conventional() {
+        assert!(ConventionalCommit::parse("Update README").is_none());
+        assert!(ConventionalCommit::parse("feat add endpoint").is_none());
+        assert!(ConventionalCommit::parse("").is_none());
+    }
+}
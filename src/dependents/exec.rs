@@ -0,0 +1,57 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::{settings::DependentSettings, staging::Staging, version::Version};
+
+use super::Dependent;
+
+/// Dependent whose file is read and written by an external executable
+#[derive(Debug)]
+pub struct ExecDependent {
+    pub settings: DependentSettings,
+    pub repo_path: PathBuf,
+}
+
+impl ExecDependent {
+    fn command(&self) -> Result<&str> {
+        self.settings
+            .command
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("Command is required for exec dependent"))
+    }
+}
+
+impl Dependent for ExecDependent {
+    fn update_version(
+        &self,
+        version: &Version,
+        _options: &super::DependentUpdateOptions,
+        staging: &mut Staging,
+    ) -> Result<Vec<PathBuf>> {
+        let file_path = &self.settings.dependent_path;
+        let file_content = staging.read(file_path)?;
+        let new_file_content = crate::edit::exec::edit(
+            self.command()?,
+            &self.repo_path,
+            file_path,
+            &file_content,
+            self.settings.selector.as_deref(),
+            &version.to_string(),
+        )?;
+        staging.write(file_path, new_file_content);
+        Ok(vec![file_path.clone()])
+    }
+
+    fn validate(&self) -> Result<()> {
+        let command = self.command()?;
+        let file_content = crate::io::read_file(&self.settings.dependent_path, &self.repo_path)?;
+        crate::edit::exec::query(
+            command,
+            &self.repo_path,
+            &self.settings.dependent_path,
+            &file_content,
+            self.settings.selector.as_deref(),
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use std::path::{Path, PathBuf};
+
+use crate::{settings::DependentSettings, staging::Staging, version::Version};
+
+use super::{Dependent, DependentType};
+
+/// Dependent whose path is a glob, updated as one dependent per match
+///
+/// Helm dependents match chart directories, the other types match files.
+#[derive(Debug)]
+pub struct GlobDependent {
+    pub settings: DependentSettings,
+    pub repo_path: PathBuf,
+}
+
+impl GlobDependent {
+    /// Return a dependent for every path that the glob matches
+    fn dependents(&self) -> Result<Vec<(PathBuf, Box<dyn Dependent>)>> {
+        let charts = matches!(self.settings.dependent_type, DependentType::Helm);
+        expand(&self.settings.dependent_path, &self.repo_path, charts)?
+            .into_iter()
+            .map(|path| {
+                let settings = DependentSettings {
+                    dependent_path: path.clone(),
+                    ..self.settings.clone()
+                };
+                let dependent = super::get_dependent(&settings, self.repo_path.clone())?;
+                Ok((path, dependent))
+            })
+            .collect()
+    }
+}
+
+impl Dependent for GlobDependent {
+    fn update_version(
+        &self,
+        version: &Version,
+        options: &super::DependentUpdateOptions,
+        staging: &mut Staging,
+    ) -> Result<Vec<PathBuf>> {
+        let mut file_paths = Vec::new();
+        for (path, dependent) in self.dependents()? {
+            file_paths.extend(
+                dependent
+                    .update_version(version, options, staging)
+                    .with_context(|| format!("Failed to update {}", path.display()))?,
+            );
+        }
+        Ok(file_paths)
+    }
+
+    fn post_update(&self, options: &super::DependentUpdateOptions) -> Result<Vec<PathBuf>> {
+        let mut file_paths = Vec::new();
+        for (_, dependent) in self.dependents()? {
+            file_paths.extend(dependent.post_update(options)?);
+        }
+        Ok(file_paths)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (path, dependent) in self.dependents()? {
+            dependent
+                .validate()
+                .with_context(|| format!("Invalid dependent {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Check if a dependent path is a glob rather than a single path
+///
+/// Any `*`, `?`, `[` or `{` makes the path a glob, even if no valid glob
+/// syntax follows. A file name with these characters is matched by putting
+/// them in a class, as in `[[]draft].md`.
+pub fn is_glob(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '[', '{'])
+}
+
+/// Return the directory that a glob starts from
+///
+/// This is the part of the path before the first component with a glob
+/// character, so every match is under it.
+pub fn base_dir(pattern: &Path) -> PathBuf {
+    pattern
+        .components()
+        .take_while(|component| !is_glob(Path::new(component.as_os_str())))
+        .collect()
+}
+
+/// Build a matcher for paths relative to the repository root
+///
+/// `*` does not match a path separator, `**` matches any number of
+/// directories.
+pub fn matcher(pattern: &Path) -> Result<GlobMatcher> {
+    let pattern = pattern.strip_prefix(".").unwrap_or(pattern);
+    Ok(GlobBuilder::new(&pattern.to_string_lossy())
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("Invalid glob '{}'", pattern.display()))?
+        .compile_matcher())
+}
+
+/// Find the files, or with `charts` the chart directories, a glob matches
+///
+/// Files ignored by git and the `.git` directory are skipped, other hidden
+/// files are not. Returns the paths relative to the
+/// repository root, sorted. Fails if the glob matches nothing.
+pub fn expand(pattern: &Path, repo_path: &Path, charts: bool) -> Result<Vec<PathBuf>> {
+    let matcher = matcher(pattern)?;
+    let mut paths = Vec::new();
+    let base = repo_path.join(base_dir(pattern));
+    if base.is_dir() {
+        let walker = ::ignore::WalkBuilder::new(&base)
+            .hidden(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+        for entry in walker {
+            let entry = entry?;
+            let matched = match charts {
+                true => entry.path().join("Chart.yaml").is_file(),
+                false => entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_file()),
+            };
+            let path = entry.path().strip_prefix(repo_path)?;
+            if matched && matcher.is_match(path) {
+                paths.push(path.to_path_buf());
+            }
+        }
+    }
+    if paths.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Glob '{}' does not match any {}",
+            pattern.display(),
+            match charts {
+                true => "chart directories",
+                false => "files",
+            }
+        ));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{init_repo, write};
+
+    #[test]
+    fn test_expand() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "deploy/values.yaml", "");
+        write(dir.path(), "deploy/prod/values-eu.yaml", "");
+        write(dir.path(), "deploy/prod/secrets.yaml", "");
+        write(dir.path(), "charts/api/Chart.yaml", "");
+        write(dir.path(), "charts/docs/README.md", "");
+        write(dir.path(), "deploy/prod/.env.production", "");
+        write(dir.path(), "docs/[draft].md", "");
+
+        let pattern = Path::new("deploy/**/values*.yaml");
+        assert!(is_glob(pattern));
+        assert_eq!(base_dir(pattern), PathBuf::from("deploy"));
+        assert_eq!(
+            expand(pattern, dir.path(), false).unwrap(),
+            vec![
+                PathBuf::from("deploy/prod/values-eu.yaml"),
+                PathBuf::from("deploy/values.yaml")
+            ]
+        );
+        assert_eq!(
+            expand(Path::new("deploy/*.yaml"), dir.path(), false).unwrap(),
+            vec![PathBuf::from("deploy/values.yaml")]
+        );
+        assert_eq!(
+            expand(Path::new("charts/*"), dir.path(), true).unwrap(),
+            vec![PathBuf::from("charts/api")]
+        );
+        assert_eq!(
+            expand(Path::new("deploy/**/.env*"), dir.path(), false).unwrap(),
+            vec![PathBuf::from("deploy/prod/.env.production")]
+        );
+        assert_eq!(
+            expand(Path::new("docs/[[]draft].md"), dir.path(), false).unwrap(),
+            vec![PathBuf::from("docs/[draft].md")]
+        );
+        let error = expand(Path::new("docs/*.txt"), dir.path(), false).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Glob 'docs/*.txt' does not match any files"
+        );
+    }
+
+    #[test]
+    fn test_expand_skips_git_dir() {
+        let (dir, _repo) = init_repo();
+        write(dir.path(), "app/config", "");
+        assert!(dir.path().join(".git/config").is_file());
+        assert_eq!(
+            expand(Path::new("**/config"), dir.path(), false).unwrap(),
+            vec![PathBuf::from("app/config")]
+        );
+    }
+}
@@ -1,11 +1,10 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use std::{
     fs,
     path::{Path, PathBuf},
-    process::Command,
 };
 
-use crate::{settings::DependentSettings, version::Version};
+use crate::{settings::DependentSettings, staging::Staging, version::Version};
 
 use super::Dependent;
 
@@ -16,28 +15,6 @@ pub struct HelmDependent {
 }
 
 impl HelmDependent {
-    fn run_helm_dependency_update(&self, chart_dir: &Path) -> Result<()> {
-        let output = Command::new("helm")
-            .arg("dependency")
-            .arg("update")
-            .current_dir(chart_dir)
-            .output()
-            .with_context(|| {
-                format!(
-                    "Failed to run 'helm dependency update' in {}",
-                    chart_dir.display()
-                )
-            })?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!(
-                "helm dependency update failed: {}",
-                stderr.trim()
-            ));
-        }
-        Ok(())
-    }
-
     fn collect_dependency_artifacts(&self, chart_dir: &Path) -> Result<Vec<PathBuf>> {
         let chart_lock_path = chart_dir.join("Chart.lock");
         let mut file_paths = Vec::new();
@@ -66,7 +43,8 @@ impl Dependent for HelmDependent {
     fn update_version(
         &self,
         version: &Version,
-        options: &super::DependentUpdateOptions,
+        _options: &super::DependentUpdateOptions,
+        staging: &mut Staging,
     ) -> Result<Vec<PathBuf>> {
         let selector = self
             .settings
@@ -75,15 +53,41 @@ impl Dependent for HelmDependent {
             .ok_or_else(|| anyhow::anyhow!("Selector is required for Helm dependent"))?;
         let chart_dir = &self.settings.dependent_path;
         let chart_yaml_path = chart_dir.join("Chart.yaml");
-        let file_content = crate::io::read_file(&chart_yaml_path, &self.repo_path)?;
+        let file_content = staging.read(&chart_yaml_path)?;
         let new_file_content =
             crate::edit::yaml::edit(&file_content, &selector, &version.to_string())?;
-        crate::io::write_file(&chart_yaml_path, &self.repo_path, new_file_content.as_str())?;
-        let mut file_paths = vec![chart_yaml_path.clone()];
-        if options.helm_dependency_update {
-            self.run_helm_dependency_update(&self.repo_path.join(chart_dir))?;
-            file_paths.extend(self.collect_dependency_artifacts(chart_dir)?);
+        staging.write(&chart_yaml_path, new_file_content);
+        Ok(vec![chart_yaml_path])
+    }
+
+    fn post_update(&self, options: &super::DependentUpdateOptions) -> Result<Vec<PathBuf>> {
+        if !options.helm_dependency_update {
+            return Ok(Vec::new());
         }
-        Ok(file_paths)
+        let chart_dir = &self.settings.dependent_path;
+        crate::hooks::run(
+            "helm dependency update",
+            &self.repo_path.join(chart_dir),
+            &[],
+        )?;
+        self.collect_dependency_artifacts(chart_dir)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let chart_yaml_path = self.settings.dependent_path.join("Chart.yaml");
+        if !self.repo_path.join(&chart_yaml_path).is_file() {
+            return Err(anyhow::anyhow!(
+                "Chart directory {} has no Chart.yaml",
+                self.settings.dependent_path.display()
+            ));
+        }
+        let selector = self
+            .settings
+            .selector
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Selector is required for Helm dependent"))?;
+        let file_content = crate::io::read_file(&chart_yaml_path, &self.repo_path)?;
+        crate::edit::yaml::query(&file_content, selector)?;
+        Ok(())
     }
 }
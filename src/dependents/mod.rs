@@ -1,20 +1,30 @@
 use anyhow::Result;
 use std::{fmt::Debug, path::PathBuf};
 
-use crate::{settings::DependentSettings, version::Version};
+use crate::{settings::DependentSettings, staging::Staging, version::Version};
+use schemars::JsonSchema;
 use serde::Deserialize;
 
+mod exec;
+pub(crate) mod glob;
 mod helm;
 mod regex;
 mod toml;
 mod yaml;
 
-#[derive(Deserialize, Debug, Clone)]
+/// The kind of file a dependent updates
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum DependentType {
+    /// Any file, the version is queried and edited by `command`
+    Exec,
+    /// Helm chart directory, the version is at `selector` in `Chart.yaml`
     Helm,
+    /// Any text file, the `selector` regular expression is replaced
     Regex,
+    /// TOML file with the version at `selector`
     Toml,
+    /// YAML file with the version at `selector`
     Yaml,
 }
 
@@ -22,7 +32,17 @@ pub fn get_dependent(
     dependent_settings: &DependentSettings,
     repo_path: PathBuf,
 ) -> Result<Box<dyn Dependent>> {
+    if glob::is_glob(&dependent_settings.dependent_path) {
+        return Ok(Box::new(glob::GlobDependent {
+            settings: dependent_settings.clone(),
+            repo_path,
+        }));
+    }
     match dependent_settings.dependent_type {
+        DependentType::Exec => Ok(Box::new(exec::ExecDependent {
+            settings: dependent_settings.clone(),
+            repo_path,
+        })),
         DependentType::Helm => Ok(Box::new(helm::HelmDependent {
             settings: dependent_settings.clone(),
             repo_path,
@@ -43,12 +63,24 @@ pub fn get_dependent(
 }
 
 pub trait Dependent: Debug {
-    /// Update the dependent and return the list of files it modified.
+    /// Stage the updated dependent and return the list of files it modified.
     fn update_version(
         &self,
         version: &Version,
         options: &DependentUpdateOptions,
+        staging: &mut Staging,
     ) -> Result<Vec<PathBuf>>;
+
+    /// Run the steps that need the updated files on disk
+    ///
+    /// Called after the staged files are written. Returns the additional
+    /// files it modified.
+    fn post_update(&self, _options: &DependentUpdateOptions) -> Result<Vec<PathBuf>> {
+        Ok(Vec::new())
+    }
+
+    /// Check that the dependent can be updated without modifying any files
+    fn validate(&self) -> Result<()>;
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -1,8 +1,8 @@
 use anyhow::Result;
-use regex::RegexBuilder;
+use regex::{Regex, RegexBuilder};
 use std::path::PathBuf;
 
-use crate::{settings::DependentSettings, version::Version};
+use crate::{settings::DependentSettings, staging::Staging, version::Version};
 
 use super::Dependent;
 
@@ -17,16 +17,63 @@ impl Dependent for RegexDependent {
         &self,
         version: &Version,
         _options: &super::DependentUpdateOptions,
+        staging: &mut Staging,
     ) -> Result<Vec<PathBuf>> {
-        let file_content = crate::io::read_file(&self.settings.dependent_path, &self.repo_path)?;
+        let file_content = staging.read(&self.settings.dependent_path)?;
         let new_file_content = update_regex(&file_content, version, &self.settings)?;
-        crate::io::write_file(
-            &self.settings.dependent_path,
-            &self.repo_path,
-            new_file_content.as_str(),
-        )?;
+        staging.write(&self.settings.dependent_path, new_file_content);
         Ok(vec![self.settings.dependent_path.clone()])
     }
+
+    fn validate(&self) -> Result<()> {
+        let file_content = crate::io::read_file(&self.settings.dependent_path, &self.repo_path)?;
+        let pattern = build_pattern(&self.settings)?;
+        check_matches(&file_content, &pattern, &self.settings)
+    }
+}
+
+fn build_pattern(settings: &DependentSettings) -> Result<Regex> {
+    let selector = settings
+        .selector
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Selector is required for regex dependent"))?;
+    Ok(RegexBuilder::new(selector).multi_line(true).build()?)
+}
+
+/// Check that the selector matches, and as many times as expected
+fn check_matches(file_content: &str, pattern: &Regex, settings: &DependentSettings) -> Result<()> {
+    let matches = pattern.find_iter(file_content).count();
+    match settings.expect_matches {
+        Some(expected) if matches != expected => Err(anyhow::anyhow!(
+            "Selector '{}' matches {} times in {}, expected {}",
+            pattern.as_str(),
+            matches,
+            settings.dependent_path.display(),
+            expected
+        )),
+        None if matches == 0 => Err(anyhow::anyhow!(
+            "Selector '{}' does not match anything in {}",
+            pattern.as_str(),
+            settings.dependent_path.display()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Build the replacement for the selector matches
+///
+/// `{{version}}` becomes the version and `{{name}}` becomes a reference to
+/// the named capture group `name`. Replacements without a `replace` setting
+/// are the plain version.
+fn build_replacement(pattern: &Regex, version: &Version, settings: &DependentSettings) -> String {
+    let Some(replace) = &settings.replace else {
+        return version.to_string();
+    };
+    let mut replacement = replace.replace("{{version}}", &version.to_string());
+    for name in pattern.capture_names().flatten() {
+        replacement = replacement.replace(&format!("{{{{{}}}}}", name), &format!("${{{}}}", name));
+    }
+    replacement
 }
 
 fn update_regex(
@@ -34,17 +81,13 @@ fn update_regex(
     version: &Version,
     settings: &DependentSettings,
 ) -> Result<String> {
-    let selector = settings
-        .selector
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("Selector is required for regex dependent"))?;
-    let replace = &settings
-        .replace
-        .clone()
-        .unwrap_or(version.to_string())
-        .replace("{{version}}", &format!("{}", version));
-    let pattern = RegexBuilder::new(selector).multi_line(true).build()?;
-    let new_file_content = pattern.replace(file_content, replace.as_str());
+    let pattern = build_pattern(settings)?;
+    check_matches(file_content, &pattern, settings)?;
+    let replacement = build_replacement(&pattern, version, settings);
+    let new_file_content = match settings.replace_all {
+        true => pattern.replace_all(file_content, replacement.as_str()),
+        false => pattern.replace(file_content, replacement.as_str()),
+    };
     Ok(new_file_content.into_owned())
 }
 
@@ -63,6 +106,9 @@ mod tests {
             dependent_path: PathBuf::from("Cargo.toml"),
             selector: Some(r#"(\d+\.\d+\.\d+)"#.to_string()),
             replace: None,
+            replace_all: false,
+            expect_matches: None,
+            command: None,
         };
         let new_file_content = update_regex(file_content, &version, &settings).unwrap();
         assert_eq!(new_file_content, r#"version = "0.2.0""#);
@@ -77,6 +123,9 @@ mod tests {
             dependent_path: PathBuf::from("Cargo.toml"),
             selector: Some(r#"version = "(.*)""#.to_string()),
             replace: Some(r#"version = "{{version}}""#.to_string()),
+            replace_all: false,
+            expect_matches: None,
+            command: None,
         };
         let new_file_content = update_regex(file_content, &version, &settings).unwrap();
         assert_eq!(new_file_content, r#"version = "0.2.0""#);
@@ -94,6 +143,9 @@ version = "0.1.0"
             dependent_path: PathBuf::from("Cargo.toml"),
             selector: Some(r#"version = "(.*)""#.to_string()),
             replace: Some(r#"version = "{{version}}""#.to_string()),
+            replace_all: false,
+            expect_matches: None,
+            command: None,
         };
         let new_file_content = update_regex(file_content, &version, &settings).unwrap();
         assert_eq!(
@@ -104,4 +156,54 @@ version = "0.2.0"
 "#
         );
     }
+
+    #[test]
+    fn test_update_regex_all_with_named_groups() {
+        let file_content = "image: registry/api:0.1.0\nimage: mirror/api:0.1.0\n";
+        let version = "0.2.0".to_version();
+        let mut settings: DependentSettings = serde_yaml::from_str(
+            r#"
+type: regex
+path: deploy/values.yaml
+selector: 'image: (?P<registry>[\w/]+):\d+\.\d+\.\d+'
+replace: 'image: {{registry}}:{{version}}'
+expect_matches: 2
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            update_regex(file_content, &version, &settings).unwrap(),
+            "image: registry/api:0.2.0\nimage: mirror/api:0.1.0\n"
+        );
+        settings.replace_all = true;
+        assert_eq!(
+            update_regex(file_content, &version, &settings).unwrap(),
+            "image: registry/api:0.2.0\nimage: mirror/api:0.2.0\n"
+        );
+    }
+
+    #[test]
+    fn test_update_regex_match_count() {
+        let version = "0.2.0".to_version();
+        let mut settings: DependentSettings = serde_yaml::from_str(
+            r#"
+type: regex
+path: README.md
+selector: 'api:\d+\.\d+\.\d+'
+replace: 'api:{{version}}'
+"#,
+        )
+        .unwrap();
+        let error = update_regex("api:latest", &version, &settings).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r"Selector 'api:\d+\.\d+\.\d+' does not match anything in README.md"
+        );
+        settings.expect_matches = Some(1);
+        let error = update_regex("api:0.1.0 api:0.1.0", &version, &settings).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r"Selector 'api:\d+\.\d+\.\d+' matches 2 times in README.md, expected 1"
+        );
+    }
 }
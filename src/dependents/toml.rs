@@ -1,7 +1,7 @@
 use anyhow::Result;
 use std::path::PathBuf;
 
-use crate::{settings::DependentSettings, version::Version};
+use crate::{settings::DependentSettings, staging::Staging, version::Version};
 
 use super::Dependent;
 
@@ -17,18 +17,29 @@ impl Dependent for TomlDependent {
         &self,
         version: &Version,
         _options: &super::DependentUpdateOptions,
+        staging: &mut Staging,
     ) -> Result<Vec<PathBuf>> {
         let file_path = &self.settings.dependent_path;
-        let repo_path = &self.repo_path;
         let selector = self
             .settings
             .selector
             .clone()
             .ok_or_else(|| anyhow::anyhow!("Selector is required for TOML dependent"))?;
-        let file_content = crate::io::read_file(file_path, repo_path)?;
+        let file_content = staging.read(file_path)?;
         let new_file_content =
             crate::edit::toml::edit(&file_content, &selector, &version.to_string())?;
-        crate::io::write_file(file_path, repo_path, new_file_content.as_str())?;
+        staging.write(file_path, new_file_content);
         Ok(vec![self.settings.dependent_path.clone()])
     }
+
+    fn validate(&self) -> Result<()> {
+        let selector = self
+            .settings
+            .selector
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Selector is required for TOML dependent"))?;
+        let file_content = crate::io::read_file(&self.settings.dependent_path, &self.repo_path)?;
+        crate::edit::toml::query(&file_content, selector)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,194 @@
+use std::{io::Write, path::Path, process::Stdio};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::LineContext;
+
+/// Response of an executable to a `query` request
+#[derive(Deserialize)]
+struct QueryResponse {
+    value: String,
+    line: usize,
+}
+
+/// Response of an executable to an `edit` request
+#[derive(Deserialize)]
+struct EditResponse {
+    content: String,
+}
+
+/// Query a file for a value with an external executable
+///
+/// The executable receives `{"operation": "query", "path", "selector",
+/// "content"}` on stdin and answers with `{"value", "line"}`, where `line`
+/// is the 1-based line number of the value.
+pub fn query(
+    command: &str,
+    repo_path: &Path,
+    path: &Path,
+    file_content: &str,
+    selector: Option<&str>,
+) -> Result<LineContext> {
+    let request = json!({
+        "operation": "query",
+        "path": path,
+        "selector": selector,
+        "content": file_content,
+    });
+    let response: QueryResponse = run(command, repo_path, &request)?;
+    Ok(LineContext {
+        value: response.value,
+        line_number: response.line,
+    })
+}
+
+/// Set a value in a file with an external executable
+///
+/// The executable receives `{"operation": "edit", "path", "selector",
+/// "content", "value"}` on stdin and answers with `{"content"}`, the
+/// complete new file content. The file itself is written by monoverse.
+pub fn edit(
+    command: &str,
+    repo_path: &Path,
+    path: &Path,
+    file_content: &str,
+    selector: Option<&str>,
+    value: &str,
+) -> Result<String> {
+    let request = json!({
+        "operation": "edit",
+        "path": path,
+        "selector": selector,
+        "content": file_content,
+        "value": value,
+    });
+    let response: EditResponse = run(command, repo_path, &request)?;
+    Ok(response.content)
+}
+
+/// Send a request to the executable and parse its response
+///
+/// The command runs in the repository root. It fails with its stderr if it
+/// exits with an error.
+fn run<T: serde::de::DeserializeOwned>(
+    command: &str,
+    repo_path: &Path,
+    request: &serde_json::Value,
+) -> Result<T> {
+    log::info!("Running '{}' with {}", command, request["operation"]);
+    let mut child = crate::hooks::shell(command)
+        .current_dir(repo_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", command))?;
+    // The request is written from another thread while the output is read,
+    // so that a command that answers while reading cannot fill the pipes
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let request = request.to_string();
+    let writer = std::thread::spawn(move || {
+        match stdin.write_all(request.as_bytes()) {
+            // A command may answer without reading the request
+            Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+            written => written,
+        }
+    });
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Failed to run '{}'", command))?;
+    let written = writer.join().expect("the request writer does not panic");
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "'{}' failed with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    written.with_context(|| format!("Failed to write the request to '{}'", command))?;
+    serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("'{}' returned an invalid response", command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Saves the request and answers with fixed values
+    const PLUGIN: &str = r#"cat > request.json; case "$(cat request.json)" in *'"operation":"query"'*) echo '{"value": "1.2.0", "line": 3}' ;; *) printf '%s' '{"content": "1.3.0\n"}' ;; esac"#;
+
+    fn request(dir: &Path) -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(dir.join("request.json")).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_exec() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Path::new("api/release.txt");
+        let line_context = query(PLUGIN, dir.path(), path, "1.2.0\n", Some("version")).unwrap();
+        assert_eq!(line_context.value, "1.2.0");
+        assert_eq!(line_context.line_number, 3);
+        assert_eq!(
+            request(dir.path()),
+            json!({
+                "operation": "query",
+                "path": "api/release.txt",
+                "selector": "version",
+                "content": "1.2.0\n",
+            })
+        );
+
+        let new_content = edit(PLUGIN, dir.path(), path, "1.2.0\n", None, "1.3.0").unwrap();
+        assert_eq!(new_content, "1.3.0\n");
+        assert_eq!(
+            request(dir.path()),
+            json!({
+                "operation": "edit",
+                "path": "api/release.txt",
+                "selector": null,
+                "content": "1.2.0\n",
+                "value": "1.3.0",
+            })
+        );
+    }
+
+    #[test]
+    fn test_exec_large_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "x".repeat(1024 * 1024);
+        // Echoes the request as it reads it, and complains on stderr
+        let error = query(
+            "tee /dev/stderr; echo",
+            dir.path(),
+            Path::new("large.txt"),
+            &content,
+            None,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "'tee /dev/stderr; echo' returned an invalid response"
+        );
+    }
+
+    #[test]
+    fn test_exec_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Path::new("VERSION");
+        let error = query("echo broken >&2; exit 2", dir.path(), path, "", None)
+            .err()
+            .unwrap();
+        assert!(error.to_string().ends_with(": broken"), "{}", error);
+        let error = query("echo '{}'", dir.path(), path, "", None)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "'echo '{}'' returned an invalid response"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+pub mod exec;
 pub mod toml;
 pub mod versionfile;
 pub mod yaml;
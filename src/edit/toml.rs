@@ -48,18 +48,18 @@ pub fn query(file_content: &str, selector: &str) -> Result<LineContext> {
 }
 
 fn get_value<'a>(doc: &'a Document, selector: &str) -> Result<&'a Item> {
-    let keys = selector.split('.').collect::<Vec<_>>();
-    if keys.len() == 1 {
-        Ok(&doc[keys[0]])
-    } else {
-        let mut item = &doc[keys[0]];
+    let mut item = doc.as_item();
+    for key in selector.split('.') {
         assert_not_array_of_tables(item)?;
-        for key in &keys[1..keys.len() - 1] {
-            item = &item[key];
-            assert_not_array_of_tables(item)?;
-        }
-        Ok(&item[keys[keys.len() - 1]])
+        item = item.get(key).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Key '{}' not found in TOML file for selector: {}",
+                key,
+                selector
+            )
+        })?;
     }
+    Ok(item)
 }
 
 fn set_value(doc: &mut Document, selector: &str, new_value: &str) -> Result<()> {
@@ -187,4 +187,15 @@ serde_json = "1.0.111"
         assert_eq!(line_context.value, "1.0.195");
         assert_eq!(line_context.line_number, 2);
     }
+
+    #[test]
+    fn test_query_missing_key() {
+        let error = query("[package]\nname = \"app\"\n", "package.version")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Key 'version' not found in TOML file for selector: package.version"
+        );
+    }
 }
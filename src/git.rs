@@ -1,30 +1,100 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
-use git2::{DiffOptions, Oid, Repository};
+use anyhow::{Context, Result};
+use git2::{
+    BlameOptions, Commit, Cred, CredentialType, ObjectType, Oid, PushOptions, RemoteCallbacks,
+    Repository, ResetType, Sort, Status, StatusOptions,
+};
 
-/// Check if a path has changed since the given commit
-pub fn has_path_changed_since<P: AsRef<Path>>(
-    repo: &Repository,
+use crate::sign::Signer;
+
+/// List the commits after `since` up to `until` that changed files under a path
+///
+/// The commits are returned newest first. Merge commits are compared to
+/// their first parent. Without `since`, all history up to `until` is listed.
+pub fn commits_touching_path<'r, P: AsRef<Path>>(
+    repo: &'r Repository,
     path: &P,
-    commit_id: Oid,
-) -> Result<bool> {
-    let previous_release = repo.find_commit(commit_id)?;
-    let head = repo.head()?.peel_to_commit()?;
-    log::debug!("Comparing {:?} to {:?}", previous_release, head);
-    let mut opts = DiffOptions::new();
-    let diff = repo.diff_tree_to_tree(
-        Some(&previous_release.tree()?),
-        Some(&head.tree()?),
-        Some(&mut opts),
-    )?;
-    Ok(diff.deltas().any(|delta| match delta.new_file().path() {
-        Some(file_path) => {
-            log::info!("Comparing {:?} to {:?}", file_path, path.as_ref());
-            file_path.starts_with(path)
+    since: Option<Oid>,
+    until: Oid,
+) -> Result<Vec<Commit<'r>>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(until)?;
+    if let Some(since) = since {
+        revwalk.hide(since)?;
+    }
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+        let touches_path = diff.deltas().any(|delta| {
+            [delta.old_file().path(), delta.new_file().path()]
+                .into_iter()
+                .flatten()
+                .any(|file_path| is_under(file_path, path.as_ref()))
+        });
+        if touches_path {
+            commits.push(commit);
         }
-        None => false,
-    }))
+    }
+    Ok(commits)
+}
+
+/// Check if a repository relative file path is under a directory
+///
+/// "." and an empty path both refer to the repository root.
+fn is_under(file_path: &Path, dir: &Path) -> bool {
+    file_path.starts_with(dir.strip_prefix(".").unwrap_or(dir))
+}
+
+/// List files with uncommitted changes under any of the given paths
+///
+/// Untracked files are included, ignored files are not. Paths are relative
+/// to the repository root, and "." or an empty path covers the whole
+/// repository.
+pub fn dirty_files<P: AsRef<Path>>(
+    repo: &Repository,
+    paths: &[P],
+) -> Result<Vec<(PathBuf, Status)>> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false);
+    let statuses = repo.statuses(Some(&mut opts))?;
+    Ok(statuses
+        .iter()
+        .filter_map(|entry| {
+            let file_path = PathBuf::from(entry.path()?);
+            paths
+                .iter()
+                .any(|path| is_under(&file_path, path.as_ref()))
+                .then_some((file_path, entry.status()))
+        })
+        .collect())
+}
+
+/// Describe a file status for user facing messages
+pub fn describe_status(status: Status) -> &'static str {
+    if status.is_conflicted() {
+        "conflicted"
+    } else if status.is_wt_new() {
+        "untracked"
+    } else if status.is_index_new() {
+        "added"
+    } else if status.is_wt_deleted() || status.is_index_deleted() {
+        "deleted"
+    } else if status.is_wt_renamed() || status.is_index_renamed() {
+        "renamed"
+    } else if status.is_wt_typechange() || status.is_index_typechange() {
+        "typechange"
+    } else {
+        "modified"
+    }
 }
 
 /// Get the commit ID for a line in a file
@@ -40,27 +110,610 @@ pub fn get_commit_id_for_line<P: AsRef<Path>>(
     }
 }
 
+/// Get the commit ID for a line in a file as of the given commit
+pub fn get_commit_id_for_line_at<P: AsRef<Path>>(
+    repo: &Repository,
+    path: &P,
+    line: usize,
+    commit_id: Oid,
+) -> Result<Oid> {
+    let mut opts = BlameOptions::new();
+    opts.newest_commit(commit_id);
+    let blame = repo.blame_file(path.as_ref(), Some(&mut opts))?;
+    match blame.get_line(line) {
+        Some(hunk) => Ok(hunk.final_commit_id()),
+        None => Err(anyhow::anyhow!("No commit found for line: {}", line)),
+    }
+}
+
+/// Read the content of a file as of the given commit
+pub fn read_file_at<P: AsRef<Path>>(repo: &Repository, path: &P, commit_id: Oid) -> Result<String> {
+    let tree = repo.find_commit(commit_id)?.tree()?;
+    let entry = tree.get_path(path.as_ref()).with_context(|| {
+        format!(
+            "File '{}' not found in commit {}",
+            path.as_ref().display(),
+            commit_id
+        )
+    })?;
+    let blob = entry.to_object(repo)?.peel_to_blob()?;
+    Ok(std::str::from_utf8(blob.content())?.to_string())
+}
+
+/// Resolve a tag, branch or other revision to a commit ID
+pub fn resolve_commit(repo: &Repository, revision: &str) -> Result<Oid> {
+    let object = repo
+        .revparse_single(revision)
+        .with_context(|| format!("Revision '{}' not found", revision))?;
+    Ok(object.peel_to_commit()?.id())
+}
+
 /// Commit changes to a list of files
-pub fn commit_files<P: AsRef<Path>>(repo: &Repository, files: &[P], message: &str) -> Result<Oid> {
+///
+/// The commit is signed when a signer is given.
+pub fn commit_files<P: AsRef<Path>>(
+    repo: &Repository,
+    files: &[P],
+    message: &str,
+    signer: Option<&Signer>,
+) -> Result<Oid> {
     let mut index = repo.index()?;
     for file in files {
         index.add_path(file.as_ref())?;
     }
     index.write()?;
     let tree_id = index.write_tree()?;
+    commit_tree(repo, tree_id, message, signer)
+}
+
+/// Commit a tree on top of HEAD and move HEAD to the new commit
+fn commit_tree(
+    repo: &Repository,
+    tree_id: Oid,
+    message: &str,
+    signer: Option<&Signer>,
+) -> Result<Oid> {
     let tree = repo.find_tree(tree_id)?;
     let author = repo.signature()?;
-    let head = repo.head()?;
+    let mut head = repo.head()?;
     let parent = head.peel_to_commit()?;
-    match repo.commit(Some("HEAD"), &author, &author, message, &tree, &[&parent]) {
-        Ok(oid) => Ok(oid),
-        Err(e) => Err(anyhow::anyhow!("Failed to commit: {}", e)),
+    let Some(signer) = signer else {
+        return match repo.commit(Some("HEAD"), &author, &author, message, &tree, &[&parent]) {
+            Ok(oid) => Ok(oid),
+            Err(e) => Err(anyhow::anyhow!("Failed to commit: {}", e)),
+        };
+    };
+    let buffer = repo.commit_create_buffer(&author, &author, message, &tree, &[&parent])?;
+    let content = std::str::from_utf8(&buffer)?;
+    let signature = signer.sign(content)?;
+    let oid = repo
+        .commit_signed(content, &signature, None)
+        .map_err(|e| anyhow::anyhow!("Failed to commit: {}", e))?;
+    let summary = message.lines().next().unwrap_or_default();
+    head.set_target(oid, &format!("commit: {}", summary))?;
+    Ok(oid)
+}
+
+/// Notes ref that records the files of each release commit
+const RELEASE_NOTES_REF: &str = "refs/notes/monoverse";
+
+/// Record the files of a release commit in a note on the commit
+pub fn record_release_files<P: AsRef<Path>>(
+    repo: &Repository,
+    commit_id: Oid,
+    files: &[P],
+) -> Result<()> {
+    let signature = repo.signature()?;
+    let note = files
+        .iter()
+        .map(|file| file.as_ref().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("\n");
+    repo.note(
+        &signature,
+        &signature,
+        Some(RELEASE_NOTES_REF),
+        commit_id,
+        &note,
+        true,
+    )?;
+    Ok(())
+}
+
+/// Return the files recorded for a release commit, None if there is no record
+pub fn release_files(repo: &Repository, commit_id: Oid) -> Result<Option<Vec<PathBuf>>> {
+    match repo.find_note(Some(RELEASE_NOTES_REF), commit_id) {
+        Ok(note) => Ok(Some(
+            note.message()
+                .unwrap_or_default()
+                .lines()
+                .map(PathBuf::from)
+                .collect(),
+        )),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// List the files a commit changed compared to its first parent
+pub fn changed_files(repo: &Repository, commit_id: Oid) -> Result<Vec<PathBuf>> {
+    let commit = repo.find_commit(commit_id)?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or(delta.old_file().path()))
+        .map(Path::to_path_buf)
+        .collect())
+}
+
+/// Return the names of the tags that point at a commit
+pub fn tags_pointing_at(repo: &Repository, commit_id: Oid) -> Result<Vec<String>> {
+    let mut tags = Vec::new();
+    for name in repo.tag_names(None)?.iter().flatten() {
+        let target = repo
+            .revparse_single(&format!("refs/tags/{}", name))?
+            .peel_to_commit();
+        if matches!(target, Ok(target) if target.id() == commit_id) {
+            tags.push(name.to_string());
+        }
+    }
+    Ok(tags)
+}
+
+/// Return the remote tracking branch that contains a commit, if any
+///
+/// The upstream of the current branch is checked, falling back to the
+/// branch with the same name on the given remote. Only the local remote
+/// tracking references are used, so the result is as fresh as the last
+/// fetch.
+pub fn pushed_to(repo: &Repository, commit_id: Oid, remote_name: &str) -> Result<Option<String>> {
+    let branch = current_branch(repo)?;
+    let upstream = match repo.branch_upstream_name(&branch) {
+        Ok(upstream) => upstream.as_str().map(str::to_string),
+        Err(_) => branch
+            .strip_prefix("refs/heads/")
+            .map(|name| format!("refs/remotes/{}/{}", remote_name, name)),
+    };
+    let Some(upstream) = upstream else {
+        return Ok(None);
+    };
+    let Ok(reference) = repo.find_reference(&upstream) else {
+        return Ok(None);
+    };
+    let upstream_id = reference.peel_to_commit()?.id();
+    let contains = upstream_id == commit_id || repo.graph_descendant_of(upstream_id, commit_id)?;
+    Ok(contains.then_some(upstream))
+}
+
+/// Move the current branch back to the parent of HEAD, discarding the changes
+pub fn reset_head(repo: &Repository) -> Result<Oid> {
+    let parent = repo.head()?.peel_to_commit()?.parent(0)?;
+    repo.reset(parent.as_object(), ResetType::Hard, None)?;
+    Ok(parent.id())
+}
+
+/// Move HEAD back to the parent of a commit, keeping the working tree
+///
+/// Does nothing if HEAD is no longer at the commit.
+pub fn uncommit(repo: &Repository, commit_id: Oid) -> Result<()> {
+    if repo.head()?.target() != Some(commit_id) {
+        return Ok(());
+    }
+    let parent = repo.find_commit(commit_id)?.parent(0)?;
+    repo.reset(parent.as_object(), ResetType::Mixed, None)?;
+    Ok(())
+}
+
+/// Create a commit that reverts HEAD
+///
+/// The commit is signed when a signer is given.
+pub fn revert_head(repo: &Repository, signer: Option<&Signer>) -> Result<Oid> {
+    let head = repo.head()?.peel_to_commit()?;
+    let mut index = repo.revert_commit(&head, &head.parent(0)?, 0, None)?;
+    if index.has_conflicts() {
+        return Err(anyhow::anyhow!("Reverting {} causes conflicts", head.id()));
     }
+    let tree_id = index.write_tree_to(repo)?;
+    repo.checkout_tree(repo.find_tree(tree_id)?.as_object(), None)?;
+    let message = format!(
+        "Revert \"{}\"\n\nThis reverts commit {}.\n",
+        head.summary().unwrap_or_default(),
+        head.id()
+    );
+    commit_tree(repo, tree_id, &message, signer)
+}
+
+/// Check if a tag exists
+pub fn tag_exists(repo: &Repository, tag: &str) -> bool {
+    repo.find_reference(&format!("refs/tags/{}", tag)).is_ok()
 }
 
 /// Tag commit
-pub fn tag_commit(repo: &Repository, commit_id: Oid, tag: &str) -> Result<()> {
+///
+/// An annotated tag is created when a message is given, otherwise the tag
+/// is lightweight. Annotated tags are signed when a signer is given.
+pub fn tag_commit(
+    repo: &Repository,
+    commit_id: Oid,
+    tag: &str,
+    message: Option<&str>,
+    signer: Option<&Signer>,
+) -> Result<()> {
     let commit = repo.find_commit(commit_id)?;
-    repo.tag(tag, commit.as_object(), &repo.signature()?, tag, false)?;
+    match (message, signer) {
+        (Some(message), Some(signer)) => {
+            create_signed_tag(repo, commit_id, tag, message, signer)?;
+        }
+        (Some(message), None) => {
+            repo.tag(tag, commit.as_object(), &repo.signature()?, message, false)?;
+        }
+        (None, _) => {
+            repo.tag_lightweight(tag, commit.as_object(), false)?;
+        }
+    };
     Ok(())
 }
+
+/// Write a signed tag object and point the tag reference at it
+///
+/// libgit2 has no API for signed tags, so the tag object is assembled in
+/// the same format git uses, with the signature appended to the message.
+fn create_signed_tag(
+    repo: &Repository,
+    commit_id: Oid,
+    tag: &str,
+    message: &str,
+    signer: &Signer,
+) -> Result<Oid> {
+    let tagger = repo.signature()?;
+    let when = tagger.when();
+    let offset = when.offset_minutes();
+    let mut payload = format!(
+        "object {}\ntype commit\ntag {}\ntagger {} <{}> {} {}{:02}{:02}\n\n{}",
+        commit_id,
+        tag,
+        tagger.name().unwrap_or_default(),
+        tagger.email().unwrap_or_default(),
+        when.seconds(),
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60,
+        message
+    );
+    if !payload.ends_with('\n') {
+        payload.push('\n');
+    }
+    payload.push_str(&signer.sign(&payload)?);
+    let tag_id = repo.odb()?.write(ObjectType::Tag, payload.as_bytes())?;
+    repo.reference(
+        &format!("refs/tags/{}", tag),
+        tag_id,
+        false,
+        &format!("tag: {}", tag),
+    )?;
+    Ok(tag_id)
+}
+
+/// Return the full reference name of the checked out branch
+pub fn current_branch(repo: &Repository) -> Result<String> {
+    let head = repo.head()?;
+    if !head.is_branch() {
+        return Err(anyhow::anyhow!("HEAD is not on a branch"));
+    }
+    head.name()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("Branch name is not valid UTF-8"))
+}
+
+/// Push references to a remote
+///
+/// All references are pushed in a single push. Nothing is pushed if a
+/// branch on the remote is not an ancestor of the local branch, for example
+/// because someone else pushed in the meantime, or if a pushed tag already
+/// exists on the remote.
+pub fn push(repo: &Repository, remote_name: &str, references: &[String]) -> Result<()> {
+    let mut remote = repo
+        .find_remote(remote_name)
+        .with_context(|| format!("Remote '{}' not found", remote_name))?;
+    let config = repo.config()?;
+    let changed = std::cell::RefCell::new(Vec::new());
+    let rejected = std::cell::RefCell::new(Vec::new());
+    let mut callbacks = remote_callbacks(&config);
+    callbacks.push_negotiation(|updates| {
+        for update in updates {
+            let name = update.dst_refname().unwrap_or_default();
+            if update.src().is_zero() {
+                continue;
+            }
+            let is_ancestor = !name.starts_with("refs/tags/")
+                && repo
+                    .graph_descendant_of(update.dst(), update.src())
+                    .unwrap_or(false);
+            if !is_ancestor {
+                changed.borrow_mut().push(name.to_string());
+            }
+        }
+        match changed.borrow().is_empty() {
+            true => Ok(()),
+            false => Err(git2::Error::from_str("remote references have changed")),
+        }
+    });
+    callbacks.push_update_reference(|name, status| {
+        if let Some(status) = status {
+            rejected.borrow_mut().push(format!("{} ({})", name, status));
+        }
+        Ok(())
+    });
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+    let refspecs = references
+        .iter()
+        .map(|reference| format!("{}:{}", reference, reference))
+        .collect::<Vec<_>>();
+    log::info!("Pushing {:?} to {}", refspecs, remote_name);
+    if let Err(e) = remote.push(&refspecs, Some(&mut options)) {
+        let changed = changed.borrow();
+        if !changed.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} has changed on the remote, nothing was pushed",
+                changed.join(", ")
+            ));
+        }
+        return Err(e).with_context(|| format!("Failed to push to '{}'", remote_name));
+    }
+    let rejected = rejected.borrow();
+    if !rejected.is_empty() {
+        return Err(anyhow::anyhow!(
+            "The remote rejected: {}",
+            rejected.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Remote callbacks that authenticate with the SSH agent or the configured
+/// credential helper
+fn remote_callbacks(config: &git2::Config) -> RemoteCallbacks<'_> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut attempts = 0;
+    callbacks.credentials(move |url, username, allowed| {
+        attempts += 1;
+        if attempts > 3 {
+            return Err(git2::Error::from_str(&format!(
+                "Authentication failed for '{}'",
+                url
+            )));
+        }
+        if allowed.contains(CredentialType::USERNAME) {
+            // Asked for first when an SSH URL has no user name
+            Cred::username(username.unwrap_or("git"))
+        } else if allowed.contains(CredentialType::SSH_KEY) {
+            Cred::ssh_key_from_agent(username.unwrap_or("git"))
+        } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            Cred::credential_helper(config, url, username)
+        } else {
+            Cred::default()
+        }
+    });
+    callbacks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{commit_all, init_repo, initial_commit, write};
+
+    #[test]
+    fn test_dirty_files() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "server/Cargo.toml", "version = \"1.0.0\"");
+        write(dir.path(), "client/package.json", "{}");
+        initial_commit(&repo, &["server/Cargo.toml", "client/package.json"]);
+        assert!(dirty_files(&repo, &["server"]).unwrap().is_empty());
+
+        write(dir.path(), "server/Cargo.toml", "version = \"1.0.1\"");
+        write(dir.path(), "server/src/main.rs", "fn main() {}");
+        write(dir.path(), "client/package.json", "{ }");
+        let mut dirty = dirty_files(&repo, &["server"]).unwrap();
+        dirty.sort_by(|a, b| a.0.cmp(&b.0));
+        let dirty = dirty
+            .into_iter()
+            .map(|(path, status)| (path, describe_status(status)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            dirty,
+            vec![
+                (PathBuf::from("server/Cargo.toml"), "modified"),
+                (PathBuf::from("server/src/main.rs"), "untracked"),
+            ]
+        );
+        assert_eq!(dirty_files(&repo, &["."]).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_tag_commit() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "VERSION", "25.3.0");
+        let commit_id = initial_commit(&repo, &["VERSION"]);
+
+        tag_commit(&repo, commit_id, "annotated", Some("Release 25.3.0"), None).unwrap();
+        let tag = repo
+            .revparse_single("refs/tags/annotated")
+            .unwrap()
+            .into_tag()
+            .unwrap();
+        assert_eq!(tag.message(), Some("Release 25.3.0"));
+        assert_eq!(tag.target_id(), commit_id);
+
+        tag_commit(&repo, commit_id, "lightweight", None, None).unwrap();
+        let reference = repo.find_reference("refs/tags/lightweight").unwrap();
+        assert_eq!(reference.target(), Some(commit_id));
+    }
+
+    fn ssh_signer(dir: &Path, repo: &Repository) -> Signer {
+        let key = dir.join("signing_key");
+        let status = std::process::Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-f"])
+            .arg(&key)
+            .status()
+            .expect("ssh-keygen is installed");
+        assert!(status.success(), "ssh-keygen failed with {}", status);
+        let mut config = repo.config().unwrap();
+        config.set_str("gpg.format", "ssh").unwrap();
+        config
+            .set_str("user.signingkey", key.to_str().unwrap())
+            .unwrap();
+        Signer::from_config(&repo.config().unwrap()).unwrap()
+    }
+
+    #[test]
+    #[ignore = "requires ssh-keygen, run with --ignored"]
+    fn test_signed_commit_and_tag() {
+        let (dir, repo) = init_repo();
+        let key_dir = tempfile::tempdir().unwrap();
+        let signer = ssh_signer(key_dir.path(), &repo);
+        write(dir.path(), "VERSION", "25.3.0");
+        let parent_id = initial_commit(&repo, &["VERSION"]);
+        write(dir.path(), "VERSION", "25.3.1");
+
+        let commit_id = commit_files(&repo, &["VERSION"], "release 25.3.1", Some(&signer)).unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(commit_id));
+        let commit = repo.find_commit(commit_id).unwrap();
+        assert_eq!(commit.parent_id(0).unwrap(), parent_id);
+        assert_eq!(commit.message(), Some("release 25.3.1"));
+        let (signature, _) = repo.extract_signature(&commit_id, None).unwrap();
+        assert!(std::str::from_utf8(&signature)
+            .unwrap()
+            .starts_with("-----BEGIN SSH SIGNATURE-----"));
+
+        tag_commit(
+            &repo,
+            commit_id,
+            "v25.3.1",
+            Some("Release 25.3.1"),
+            Some(&signer),
+        )
+        .unwrap();
+        let tag = repo
+            .revparse_single("refs/tags/v25.3.1")
+            .unwrap()
+            .into_tag()
+            .unwrap();
+        assert_eq!(tag.target_id(), commit_id);
+        assert_eq!(tag.name(), Some("v25.3.1"));
+        assert_eq!(tag.tagger().unwrap().email(), Some("test@example.com"));
+        let message = tag.message().unwrap();
+        assert!(message.starts_with("Release 25.3.1\n-----BEGIN SSH SIGNATURE-----"));
+    }
+
+    #[test]
+    fn test_commits_touching_path() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "server/Cargo.toml", "version = \"1.0.0\"");
+        let release_id = initial_commit(&repo, &["server/Cargo.toml"]);
+        write(dir.path(), "server/src/main.rs", "fn main() {}");
+        commit_all(&repo, "feat: add main");
+        write(dir.path(), "client/package.json", "{}");
+        commit_all(&repo, "feat: add client");
+        write(dir.path(), "server/src/main.rs", "fn main() { }");
+        commit_all(&repo, "fix: format main");
+
+        let messages = |path: &str| {
+            let head_id = repo.head().unwrap().target().unwrap();
+            commits_touching_path(&repo, &path, Some(release_id), head_id)
+                .unwrap()
+                .iter()
+                .map(|commit| commit.summary().unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            messages("server"),
+            vec!["fix: format main", "feat: add main"]
+        );
+        assert_eq!(messages("client"), vec!["feat: add client"]);
+        assert_eq!(messages(".").len(), 3);
+    }
+
+    fn init_remote(repo: &Repository) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        Repository::init_bare(dir.path()).unwrap();
+        repo.remote("origin", dir.path().to_str().unwrap()).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_push() {
+        let (dir, repo) = init_repo();
+        let remote_dir = init_remote(&repo);
+        write(dir.path(), "VERSION", "25.3.0");
+        initial_commit(&repo, &["VERSION"]);
+        let branch = current_branch(&repo).unwrap();
+        push(&repo, "origin", std::slice::from_ref(&branch)).unwrap();
+
+        write(dir.path(), "VERSION", "25.3.1");
+        let commit_id = commit_files(&repo, &["VERSION"], "release 25.3.1", None).unwrap();
+        tag_commit(&repo, commit_id, "v25.3.1", Some("v25.3.1"), None).unwrap();
+        tag_commit(&repo, commit_id, "unrelated", None, None).unwrap();
+        push(
+            &repo,
+            "origin",
+            &[branch.clone(), "refs/tags/v25.3.1".into()],
+        )
+        .unwrap();
+
+        let remote = Repository::open_bare(remote_dir.path()).unwrap();
+        assert_eq!(
+            remote.find_reference(&branch).unwrap().target(),
+            Some(commit_id)
+        );
+        let tag = remote.find_reference("refs/tags/v25.3.1").unwrap();
+        assert_eq!(tag.peel_to_commit().unwrap().id(), commit_id);
+        assert!(remote.find_reference("refs/tags/unrelated").is_err());
+    }
+
+    #[test]
+    fn test_push_branch_moved() {
+        let (dir, repo) = init_repo();
+        let remote_dir = init_remote(&repo);
+        write(dir.path(), "VERSION", "25.3.0");
+        let initial_id = initial_commit(&repo, &["VERSION"]);
+        let branch = current_branch(&repo).unwrap();
+        push(&repo, "origin", std::slice::from_ref(&branch)).unwrap();
+
+        let remote = Repository::open_bare(remote_dir.path()).unwrap();
+        let initial = remote.find_commit(initial_id).unwrap();
+        let signature = repo.signature().unwrap();
+        let moved_id = remote
+            .commit(
+                Some(&branch),
+                &signature,
+                &signature,
+                "someone else",
+                &initial.tree().unwrap(),
+                &[&initial],
+            )
+            .unwrap();
+
+        write(dir.path(), "VERSION", "25.3.1");
+        let commit_id = commit_files(&repo, &["VERSION"], "release 25.3.1", None).unwrap();
+        tag_commit(&repo, commit_id, "v25.3.1", Some("v25.3.1"), None).unwrap();
+        let result = push(
+            &repo,
+            "origin",
+            &[branch.clone(), "refs/tags/v25.3.1".into()],
+        );
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("has changed on the remote, nothing was pushed"));
+        assert_eq!(
+            remote.find_reference(&branch).unwrap().target(),
+            Some(moved_id)
+        );
+        assert!(remote.find_reference("refs/tags/v25.3.1").is_err());
+    }
+}
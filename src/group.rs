@@ -0,0 +1,266 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use git2::Repository;
+
+use crate::{
+    git,
+    projects::{self, ProjectFile, ReleaseOptions, ReleaseOutcome},
+    settings::{GroupSettings, Settings},
+    staging::Staging,
+    version::Version,
+};
+
+/// Result of a group release
+pub struct GroupOutcome {
+    /// The highest member version before the release
+    pub previous_version: Version,
+    /// The shared version the members were released with
+    pub version: Version,
+    /// The released members in the order of the group settings
+    pub members: Vec<Member>,
+}
+
+/// A group member and its release outcome
+pub struct Member {
+    pub name: String,
+    pub project_file: Box<dyn ProjectFile>,
+    pub outcome: ReleaseOutcome,
+}
+
+/// Release every member of a group with one shared version
+///
+/// A change in any member releases all of them. The current version of the
+/// group is the highest member version, and the next version the highest
+/// next version of the changed members, computed by each member according
+/// to the shared version scheme. Every member manifest is then updated.
+///
+/// If no member has changed since its last release, return None.
+pub fn release(
+    repo: &Repository,
+    group: &GroupSettings,
+    settings: &Settings,
+    repo_path: &Path,
+    options: &ReleaseOptions,
+    staging: &mut Staging,
+) -> Result<Option<GroupOutcome>> {
+    let members = read_members(repo, group, settings, repo_path, options.allow_dirty)?;
+    let Some(version) = next_version(repo, &members, options.force)? else {
+        return Ok(None);
+    };
+    let previous_version = current_version(&members);
+    let mut released = Vec::new();
+    for (name, project_file, content, mut outcome) in members {
+        outcome.version_context.next_version = version.clone();
+        project_file
+            .write_version(&content, &outcome.version_context, staging)
+            .with_context(|| format!("Failed to write the version of '{}'", name))?;
+        released.push(Member {
+            name,
+            project_file,
+            outcome,
+        });
+    }
+    Ok(Some(GroupOutcome {
+        previous_version,
+        version,
+        members: released,
+    }))
+}
+
+/// Return the next version of a group without editing files
+///
+/// This is the version that a forced release would produce.
+pub fn upcoming_version(
+    repo: &Repository,
+    group: &GroupSettings,
+    settings: &Settings,
+    repo_path: &Path,
+) -> Result<Version> {
+    let members = read_members(repo, group, settings, repo_path, true)?;
+    Ok(next_version(repo, &members, true)?
+        .unwrap_or_else(|| current_version(&members).bump_patch()))
+}
+
+type MemberState = (String, Box<dyn ProjectFile>, String, ReleaseOutcome);
+
+/// Read the manifest and the previous release of every member
+fn read_members(
+    repo: &Repository,
+    group: &GroupSettings,
+    settings: &Settings,
+    repo_path: &Path,
+    allow_dirty: bool,
+) -> Result<Vec<MemberState>> {
+    let mut members = Vec::new();
+    for name in &group.members {
+        let project_file = projects::get_project_file(
+            settings.project_settings(name)?.clone(),
+            repo_path.to_path_buf(),
+        );
+        if !allow_dirty {
+            project_file
+                .ensure_clean(repo)
+                .with_context(|| format!("Member '{}' is not clean", name))?;
+        }
+        let manifest_path = project_file.get_manifest_file_path()?;
+        let content = crate::io::read_file(&manifest_path, &repo_path.to_path_buf())?;
+        let version_context = project_file.version_context(&content)?;
+        let previous_release =
+            git::get_commit_id_for_line(repo, &manifest_path, version_context.line_number).ok();
+        members.push((
+            name.clone(),
+            project_file,
+            content,
+            ReleaseOutcome {
+                version_context,
+                previous_release,
+            },
+        ));
+    }
+    Ok(members)
+}
+
+fn current_version(members: &[MemberState]) -> Version {
+    members
+        .iter()
+        .map(|(_, _, _, outcome)| outcome.version_context.version.clone())
+        .max()
+        .unwrap_or(Version::parse("0.0.0"))
+}
+
+/// Compute the shared next version, or None if there is nothing to release
+fn next_version(
+    repo: &Repository,
+    members: &[MemberState],
+    force: bool,
+) -> Result<Option<Version>> {
+    let current = current_version(members);
+    let mut next: Option<Version> = None;
+    for (name, project_file, _, outcome) in members {
+        let changed = match (outcome.previous_release, force) {
+            (_, true) => true,
+            (Some(previous_release), false) => !project_file
+                .release_commits(repo, previous_release)?
+                .is_empty(),
+            (None, false) => {
+                return Err(anyhow::anyhow!(
+                    "Could not find the previous release of '{}'",
+                    name
+                ))
+            }
+        };
+        if !changed {
+            continue;
+        }
+        if let Some(version) =
+            project_file.next_version(repo, &current, outcome.previous_release)?
+        {
+            next = next.max(Some(version));
+        }
+    }
+    match next {
+        Some(version) => Ok(Some(version)),
+        None if force => Ok(Some(current.bump_patch())),
+        None => {
+            log::info!("There are no changes to the group that require a release.");
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{commit_all, init_repo, initial_commit, write};
+    use crate::version::ToVersion;
+    use std::path::PathBuf;
+
+    const CONFIG: &str = r#"
+projects:
+  api:
+    type: versionfile
+    path: api
+    manifest_path: api/VERSION
+    scheme: semver
+  worker:
+    type: versionfile
+    path: worker
+    manifest_path: worker/VERSION
+    scheme: semver
+  web:
+    type: versionfile
+    path: web
+    manifest_path: web/VERSION
+    scheme: semver
+groups:
+  backend:
+    members: [api, worker]
+"#;
+
+    #[test]
+    fn test_release_group() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "api/VERSION", "1.2.0");
+        write(dir.path(), "worker/VERSION", "1.1.0");
+        write(dir.path(), "web/VERSION", "3.0.0");
+        initial_commit(&repo, &["api/VERSION", "worker/VERSION", "web/VERSION"]);
+        let settings: Settings = serde_yaml::from_str(CONFIG).unwrap();
+        let group = settings.group_settings("backend").unwrap();
+        let options = ReleaseOptions::default();
+        let mut staging = Staging::new(dir.path());
+
+        write(dir.path(), "web/main.rs", "");
+        commit_all(&repo, "feat: add web");
+        assert!(
+            release(&repo, group, &settings, dir.path(), &options, &mut staging)
+                .unwrap()
+                .is_none()
+        );
+
+        write(dir.path(), "worker/main.rs", "");
+        commit_all(&repo, "fix: add worker");
+        let outcome = release(&repo, group, &settings, dir.path(), &options, &mut staging)
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.previous_version, "1.2.0".to_version());
+        assert_eq!(outcome.version, "1.2.1".to_version());
+        let names = outcome
+            .members
+            .iter()
+            .map(|member| member.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["api", "worker"]);
+        assert_eq!(
+            outcome.members[1].outcome.version_context.version,
+            "1.1.0".to_version()
+        );
+        assert_eq!(
+            staging.paths(),
+            vec![
+                PathBuf::from("api/VERSION"),
+                PathBuf::from("worker/VERSION")
+            ]
+        );
+        assert_eq!(staging.staged("worker/VERSION"), Some("1.2.1"));
+    }
+
+    #[test]
+    fn test_release_group_forced() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "api/VERSION", "1.2.0");
+        write(dir.path(), "worker/VERSION", "1.2.0");
+        initial_commit(&repo, &["api/VERSION", "worker/VERSION"]);
+        let settings: Settings = serde_yaml::from_str(CONFIG).unwrap();
+        let group = settings.group_settings("backend").unwrap();
+        let options = ReleaseOptions {
+            force: true,
+            ..Default::default()
+        };
+        let mut staging = Staging::new(dir.path());
+        let outcome = release(&repo, group, &settings, dir.path(), &options, &mut staging)
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.version, "1.2.1".to_version());
+    }
+}
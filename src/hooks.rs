@@ -0,0 +1,251 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result};
+use git2::Repository;
+
+use crate::{
+    git,
+    settings::{HookSettings, ProjectSettings},
+    version::Version,
+};
+
+/// The hooks of a released project and the environment they run in
+#[derive(Debug)]
+pub struct Hooks {
+    settings: HookSettings,
+    dir: PathBuf,
+    env: Vec<(&'static str, String)>,
+    /// The release paths of the project, where `post_update` changes are
+    /// picked up
+    paths: Vec<PathBuf>,
+}
+
+impl Hooks {
+    /// Describe the release of a project to its hooks
+    ///
+    /// The hooks receive the project name, the new and the previous version,
+    /// and the tag if one is created.
+    pub fn new(
+        project: &str,
+        project_settings: &ProjectSettings,
+        repo_path: &Path,
+        version: &Version,
+        previous_version: &Version,
+        tag: Option<&str>,
+    ) -> Result<Self> {
+        let mut env = vec![
+            ("MONOVERSE_PROJECT", project.to_string()),
+            ("MONOVERSE_VERSION", version.to_string()),
+            ("MONOVERSE_PREVIOUS_VERSION", previous_version.to_string()),
+        ];
+        env.extend(tag.map(|tag| ("MONOVERSE_TAG", tag.to_string())));
+        Ok(Self {
+            settings: project_settings.hooks.clone(),
+            dir: repo_path.join(&project_settings.project_path),
+            env,
+            paths: project_settings.release_paths()?,
+        })
+    }
+
+    /// The paths where changes of the `post_update` hooks are picked up
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    pub fn pre_release(&self) -> Result<()> {
+        self.run_all("pre_release", &self.settings.pre_release)
+    }
+
+    pub fn post_update(&self) -> Result<()> {
+        self.run_all("post_update", &self.settings.post_update)
+    }
+
+    pub fn post_release(&self) -> Result<()> {
+        self.run_all("post_release", &self.settings.post_release)
+    }
+
+    fn run_all(&self, stage: &str, commands: &[String]) -> Result<()> {
+        for command in commands {
+            run(command, &self.dir, &self.env)
+                .with_context(|| format!("The {} hook failed", stage))?;
+        }
+        Ok(())
+    }
+}
+
+/// Run a shell command in a directory
+///
+/// The output of the command goes to stderr, so that it does not mix with
+/// the output of monoverse. Fails if the command exits with an error.
+pub fn run(command: &str, dir: &Path, env: &[(&str, String)]) -> Result<()> {
+    log::info!("Running '{}' in {}", command, dir.display());
+    let status = shell(command)
+        .current_dir(dir)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdout(Stdio::from(std::io::stderr()))
+        .status()
+        .with_context(|| format!("Failed to run '{}' in {}", command, dir.display()))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("'{}' failed with {}", command, status));
+    }
+    Ok(())
+}
+
+/// Build a command that runs a command line with the system shell
+pub(crate) fn shell(command: &str) -> Command {
+    let (shell, flag) = match cfg!(windows) {
+        true => ("cmd", "/C"),
+        false => ("sh", "-c"),
+    };
+    let mut shell = Command::new(shell);
+    shell.arg(flag).arg(command);
+    shell
+}
+
+/// Contents of the uncommitted files under the paths that hooks may change
+#[derive(Debug)]
+pub struct Snapshot {
+    paths: Vec<PathBuf>,
+    files: HashMap<PathBuf, Option<Vec<u8>>>,
+}
+
+/// Record the uncommitted files under `paths` so that later changes can be
+/// found
+///
+/// Only these paths are watched, the [paths](Hooks::paths) of the released
+/// projects, so that the rest of the repository is not read.
+pub fn snapshot(repo: &Repository, repo_path: &Path, paths: &[PathBuf]) -> Result<Snapshot> {
+    let files = git::dirty_files(repo, paths)?
+        .into_iter()
+        .map(|(path, _)| {
+            let content = std::fs::read(repo_path.join(&path)).ok();
+            (path, content)
+        })
+        .collect();
+    Ok(Snapshot {
+        paths: paths.to_vec(),
+        files,
+    })
+}
+
+/// Return the files under the watched paths that changed since the snapshot
+/// was taken
+pub fn changed_since(
+    repo: &Repository,
+    repo_path: &Path,
+    snapshot: &Snapshot,
+) -> Result<Vec<PathBuf>> {
+    Ok(git::dirty_files(repo, &snapshot.paths)?
+        .into_iter()
+        .filter(|(path, _)| match snapshot.files.get(path) {
+            Some(content) => &std::fs::read(repo_path.join(path)).ok() != content,
+            None => true,
+        })
+        .map(|(path, _)| path)
+        .collect())
+}
+
+/// Return the files that changed since the snapshot with their content then
+///
+/// Files that had no uncommitted changes get their committed content, and
+/// files that did not exist get None.
+pub fn originals_since(
+    repo: &Repository,
+    repo_path: &Path,
+    snapshot: &Snapshot,
+) -> Result<Vec<(PathBuf, Option<Vec<u8>>)>> {
+    let head = repo.head()?.peel_to_tree()?;
+    changed_since(repo, repo_path, snapshot)?
+        .into_iter()
+        .map(|path| {
+            let original = match snapshot.files.get(&path) {
+                Some(content) => content.clone(),
+                None => match head.get_path(&path) {
+                    Ok(entry) => Some(entry.to_object(repo)?.peel_to_blob()?.content().to_vec()),
+                    Err(_) => None,
+                },
+            };
+            Ok((path, original))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{init_repo, initial_commit, write};
+
+    #[test]
+    fn test_hooks() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "api/VERSION", "1.2.0");
+        write(dir.path(), "api/openapi.json", "{}");
+        write(dir.path(), "notes.txt", "draft");
+        initial_commit(&repo, &["api/VERSION", "api/openapi.json"]);
+        let project_settings: ProjectSettings = serde_yaml::from_str(
+            r#"
+type: versionfile
+path: api
+manifest_path: api/VERSION
+hooks:
+  pre_release:
+    - test "$MONOVERSE_VERSION" = 1.3.0
+  post_update:
+    - 'echo "$MONOVERSE_VERSION" > openapi.json'
+    - echo "$MONOVERSE_PROJECT $MONOVERSE_PREVIOUS_VERSION $MONOVERSE_TAG" > release.txt
+    - echo reviewed >> ../notes.txt
+  post_release:
+    - exit 3
+"#,
+        )
+        .unwrap();
+        let hooks = Hooks::new(
+            "api",
+            &project_settings,
+            dir.path(),
+            &Version::parse("1.3.0"),
+            &Version::parse("1.2.0"),
+            Some("api-1.3.0"),
+        )
+        .unwrap();
+        hooks.pre_release().unwrap();
+
+        let snapshot = snapshot(&repo, dir.path(), hooks.paths()).unwrap();
+        hooks.post_update().unwrap();
+        // notes.txt is outside the project, so it is not watched
+        let mut changed = changed_since(&repo, dir.path(), &snapshot).unwrap();
+        changed.sort();
+        assert_eq!(
+            changed,
+            vec![
+                PathBuf::from("api/openapi.json"),
+                PathBuf::from("api/release.txt")
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("api/release.txt")).unwrap(),
+            "api 1.2.0 api-1.3.0\n"
+        );
+
+        let mut originals = originals_since(&repo, dir.path(), &snapshot).unwrap();
+        originals.sort();
+        assert_eq!(
+            originals,
+            vec![
+                (PathBuf::from("api/openapi.json"), Some(b"{}".to_vec())),
+                (PathBuf::from("api/release.txt"), None)
+            ]
+        );
+
+        let error = hooks.post_release().unwrap_err();
+        assert_eq!(error.to_string(), "The post_release hook failed");
+        assert!(error
+            .root_cause()
+            .to_string()
+            .starts_with("'exit 3' failed"));
+    }
+}
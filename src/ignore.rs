@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use git2::Commit;
+use regex::Regex;
+
+use crate::settings::IgnoreRule;
+
+/// Ignore rules with their patterns compiled
+#[derive(Debug)]
+pub struct IgnoreRules {
+    rules: Vec<CompiledRule>,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    message: Option<Regex>,
+    author: Option<Regex>,
+    trailer: Option<String>,
+}
+
+impl IgnoreRules {
+    /// Compile the rules, failing on invalid patterns and empty rules
+    pub fn new(rules: &[IgnoreRule]) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                if rule.message.is_none() && rule.author.is_none() && rule.trailer.is_none() {
+                    return Err(anyhow::anyhow!(
+                        "Ignore rule must define at least one of 'message', 'author' or 'trailer'"
+                    ));
+                }
+                Ok(CompiledRule {
+                    message: rule.message.as_deref().map(compile).transpose()?,
+                    author: rule.author.as_deref().map(compile).transpose()?,
+                    trailer: rule.trailer.clone(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Check if a commit matches any of the rules
+    pub fn is_ignored(&self, commit: &Commit) -> Result<bool> {
+        for rule in &self.rules {
+            if rule.matches(commit)? {
+                log::info!(
+                    "Ignoring commit {} '{}'",
+                    commit.id(),
+                    commit.summary().unwrap_or_default()
+                );
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Remove the commits that match any of the rules
+    pub fn filter<'r>(&self, commits: Vec<Commit<'r>>) -> Result<Vec<Commit<'r>>> {
+        let mut kept = Vec::new();
+        for commit in commits {
+            if !self.is_ignored(&commit)? {
+                kept.push(commit);
+            }
+        }
+        Ok(kept)
+    }
+}
+
+impl CompiledRule {
+    /// Check if a commit matches every condition of the rule
+    ///
+    /// The message pattern is matched against the full commit message and
+    /// the author pattern against `Name <email>`. The trailer is matched by
+    /// key, ignoring case.
+    fn matches(&self, commit: &Commit) -> Result<bool> {
+        let message = commit.message().unwrap_or_default();
+        if let Some(pattern) = &self.message {
+            if !pattern.is_match(message) {
+                return Ok(false);
+            }
+        }
+        if let Some(pattern) = &self.author {
+            let author = commit.author();
+            let author = format!(
+                "{} <{}>",
+                author.name().unwrap_or_default(),
+                author.email().unwrap_or_default()
+            );
+            if !pattern.is_match(&author) {
+                return Ok(false);
+            }
+        }
+        if let Some(key) = &self.trailer {
+            let trailers = git2::message_trailers_strs(message)?;
+            if !trailers
+                .iter()
+                .any(|(trailer_key, _)| trailer_key.eq_ignore_ascii_case(key))
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).with_context(|| format!("Invalid ignore pattern: {}", pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{commit_all, init_repo, initial_commit, write};
+
+    fn rule(yaml: &str) -> IgnoreRule {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "file", "0");
+        initial_commit(&repo, &["file"]);
+        write(dir.path(), "file", "1");
+        let commit_id = commit_all(
+            &repo,
+            "chore(deps): update serde [skip release]\n\nBody\n\nRelease-Note: none\n",
+        );
+        let commit = repo.find_commit(commit_id).unwrap();
+
+        let matching = [
+            rule(r"message: '\[skip release\]'"),
+            rule("author: '^Test <test@example\\.com>$'"),
+            rule("trailer: release-note"),
+            rule("message: '^chore\\(deps\\)'\nauthor: Test"),
+        ];
+        for rule in matching {
+            let rules = IgnoreRules::new(&[rule]).unwrap();
+            assert!(rules.is_ignored(&commit).unwrap());
+        }
+        let non_matching = [
+            rule("message: '^feat'"),
+            rule("author: renovate"),
+            rule("trailer: Skip-Release"),
+            rule("message: '^chore\\(deps\\)'\nauthor: renovate"),
+        ];
+        let rules = IgnoreRules::new(&non_matching).unwrap();
+        assert!(!rules.is_ignored(&commit).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(IgnoreRules::new(&[rule("{}")]).is_err());
+        let error = IgnoreRules::new(&[rule("message: '(unclosed'")]).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid ignore pattern: (unclosed");
+    }
+}
@@ -0,0 +1,370 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use git2::Repository;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    projects::{self, ProjectType},
+    settings::{ProjectSettings, VersionScheme},
+    version::Version,
+};
+
+/// Format of the generated configuration file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    #[default]
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => "yaml",
+            ConfigFormat::Toml => "toml",
+            ConfigFormat::Json => "json",
+        }
+    }
+}
+
+/// Configuration proposed by `init`
+#[derive(Serialize, Debug)]
+pub struct InitConfig {
+    pub projects: BTreeMap<String, InitProject>,
+}
+
+/// A project found in the repository
+#[derive(Serialize, Debug, Clone)]
+pub struct InitProject {
+    #[serde(rename = "type")]
+    pub project_type: ProjectType,
+    pub path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scheme: Option<VersionScheme>,
+    pub tag_prefix: String,
+}
+
+/// Manifest file names and the project type they are detected as
+///
+/// A directory with several manifests becomes a single project using the
+/// first match in this list. TOML manifests are tried with each selector.
+const MANIFESTS: [(&str, ProjectType, &[&str]); 5] = [
+    ("Cargo.toml", ProjectType::Rust, &[]),
+    ("package.json", ProjectType::Node, &[]),
+    ("Chart.yaml", ProjectType::Helm, &[]),
+    (
+        "pyproject.toml",
+        ProjectType::Toml,
+        &["project.version", "tool.poetry.version"],
+    ),
+    ("VERSION", ProjectType::Versionfile, &[]),
+];
+
+/// Scan the repository for projects
+///
+/// Files ignored by git are skipped. A manifest only becomes a project if
+/// its version can be read, so for example Cargo workspace roots and
+/// private npm packages without a version are left out. Tag prefixes are
+/// detected from the existing tags.
+pub fn scan(repo: &Repository, repo_path: &Path) -> Result<InitConfig> {
+    let mut found: BTreeMap<PathBuf, InitProject> = BTreeMap::new();
+    let walker = ::ignore::WalkBuilder::new(repo_path)
+        .hidden(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+    for entry in walker {
+        let entry = entry?;
+        if !entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            continue;
+        }
+        let manifest_path = entry.path().strip_prefix(repo_path)?.to_path_buf();
+        let Some(project) = detect(&manifest_path, repo_path) else {
+            continue;
+        };
+        match found.get(&project.path) {
+            Some(existing) if priority(existing) <= priority(&project) => {
+                log::info!(
+                    "Skipping {}, {} already has a {:?} project",
+                    manifest_path.display(),
+                    project.path.display(),
+                    existing.project_type
+                );
+            }
+            _ => {
+                found.insert(project.path.clone(), project);
+            }
+        }
+    }
+
+    let names = project_names(found.keys(), repo_path);
+    let tags = repo
+        .tag_names(None)?
+        .iter()
+        .flatten()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    let single = found.len() == 1;
+    let projects = found
+        .into_iter()
+        .map(|(path, mut project)| {
+            let name = names[&path].clone();
+            project.tag_prefix =
+                detect_tag_prefix(&name, &tags, single).unwrap_or_else(|| format!("{}-", name));
+            (name, project)
+        })
+        .collect();
+    Ok(InitConfig { projects })
+}
+
+/// Serialize the configuration in the given format
+pub fn render(config: &InitConfig, format: ConfigFormat) -> Result<String> {
+    Ok(match format {
+        ConfigFormat::Yaml => serde_yaml::to_string(config)?,
+        ConfigFormat::Toml => toml_edit::ser::to_string_pretty(config)?,
+        ConfigFormat::Json => serde_json::to_string_pretty(config)? + "\n",
+    })
+}
+
+/// Detect a project from a manifest file path
+fn detect(manifest_path: &Path, repo_path: &Path) -> Option<InitProject> {
+    let file_name = manifest_path.file_name()?.to_str()?;
+    let (_, project_type, selectors) = MANIFESTS.iter().find(|(name, ..)| *name == file_name)?;
+    let project_path = match manifest_path.parent() {
+        Some(parent) if parent != Path::new("") => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let selectors = match selectors.is_empty() {
+        true => vec![None],
+        false => selectors.iter().map(|selector| Some(*selector)).collect(),
+    };
+    for selector in selectors {
+        let project = InitProject {
+            project_type: project_type.clone(),
+            path: project_path.clone(),
+            manifest_path: matches!(project_type, ProjectType::Toml | ProjectType::Versionfile)
+                .then(|| manifest_path.to_path_buf()),
+            selector: selector.map(str::to_string),
+            scheme: None,
+            tag_prefix: String::new(),
+        };
+        match read_version(&project, repo_path) {
+            Ok(version) => {
+                return Some(InitProject {
+                    scheme: (!looks_like_calver(&version)).then_some(VersionScheme::Semver),
+                    ..project
+                })
+            }
+            Err(e) => log::info!("Skipping {}: {}", manifest_path.display(), e),
+        }
+    }
+    None
+}
+
+/// Read the current version with the same code as a release would
+fn read_version(project: &InitProject, repo_path: &Path) -> Result<Version> {
+    let settings: ProjectSettings = serde_json::from_value(serde_json::to_value(project)?)?;
+    let project_file = projects::get_project_file(settings, repo_path.to_path_buf());
+    let content = crate::io::read_file(
+        &project_file.get_manifest_file_path()?,
+        &repo_path.to_path_buf(),
+    )?;
+    let version_context = project_file.version_context(&content)?;
+    Ok(version_context.version)
+}
+
+/// Return true if the version is a plausible `YY.MM.MICRO` calendar version
+fn looks_like_calver(version: &Version) -> bool {
+    (20..100).contains(&version.major) && (1..=12).contains(&version.minor)
+}
+
+fn priority(project: &InitProject) -> usize {
+    MANIFESTS
+        .iter()
+        .position(|(_, project_type, _)| *project_type == project.project_type)
+        .unwrap_or(usize::MAX)
+}
+
+/// Name projects after their directory
+///
+/// The root project is named after the repository directory. Projects
+/// whose directory names collide are named after their full path instead.
+fn project_names<'a>(
+    paths: impl Iterator<Item = &'a PathBuf>,
+    repo_path: &Path,
+) -> HashMap<PathBuf, String> {
+    let paths = paths.collect::<Vec<_>>();
+    let short_name = |path: &Path| match path.file_name() {
+        Some(name) if path != Path::new(".") => name.to_string_lossy().to_string(),
+        _ => repo_path
+            .canonicalize()
+            .ok()
+            .and_then(|path| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| "root".to_string()),
+    };
+    let mut counts = HashMap::new();
+    for path in &paths {
+        *counts.entry(short_name(path)).or_insert(0) += 1;
+    }
+    paths
+        .into_iter()
+        .map(|path| {
+            let name = short_name(path);
+            let name = match counts[&name] {
+                1 => name,
+                _ => path
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("-"),
+            };
+            (path.clone(), name)
+        })
+        .collect()
+}
+
+/// Detect the tag prefix of a project from the existing tags
+///
+/// A prefix belongs to the project if it is the project name followed by
+/// separators and an optional `v`, for example `api-`, `api/v` or `api@`.
+/// Without a project name, as in `v1.2.0`, it only belongs to the project
+/// if it is the only one. The most common matching prefix wins.
+fn detect_tag_prefix(name: &str, tags: &[String], single: bool) -> Option<String> {
+    let version_tag = Regex::new(r"^(?P<prefix>.*?)\d+\.\d+\.\d+$").unwrap();
+    let separators = Regex::new(r"^[-_/@.]*v?$").unwrap();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for tag in tags {
+        let Some(captures) = version_tag.captures(tag) else {
+            continue;
+        };
+        let prefix = captures.name("prefix").unwrap().as_str();
+        let belongs = match prefix.strip_prefix(name) {
+            Some(rest) => separators.is_match(rest),
+            None => single && separators.is_match(prefix),
+        };
+        if belongs {
+            *counts.entry(prefix).or_insert(0) += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+        .map(|(prefix, _)| prefix.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{init_repo, initial_commit, write};
+
+    #[test]
+    fn test_scan() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), ".gitignore", "node_modules/\n");
+        write(
+            dir.path(),
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"server\"]\n",
+        );
+        write(
+            dir.path(),
+            "server/Cargo.toml",
+            "[package]\nname = \"server\"\nversion = \"25.3.1\"\n",
+        );
+        write(
+            dir.path(),
+            "client/package.json",
+            "{\n  \"name\": \"client\",\n  \"version\": \"1.4.0\"\n}\n",
+        );
+        write(
+            dir.path(),
+            "client/node_modules/dep/package.json",
+            "{\n  \"version\": \"1.0.0\"\n}\n",
+        );
+        write(
+            dir.path(),
+            "tools/pyproject.toml",
+            "[tool.poetry]\nname = \"tools\"\nversion = \"0.2.0\"\n",
+        );
+        let commit_id = initial_commit(&repo, &[".gitignore"]);
+        let commit = repo.find_object(commit_id, None).unwrap();
+        repo.tag_lightweight("client@1.3.0", &commit, false)
+            .unwrap();
+        repo.tag_lightweight("client@1.4.0", &commit, false)
+            .unwrap();
+        repo.tag_lightweight("v1.0.0", &commit, false).unwrap();
+
+        let config = scan(&repo, dir.path()).unwrap();
+        assert_eq!(
+            config.projects.keys().collect::<Vec<_>>(),
+            vec!["client", "server", "tools"]
+        );
+        let server = &config.projects["server"];
+        assert_eq!(server.project_type, ProjectType::Rust);
+        assert_eq!(server.path, PathBuf::from("server"));
+        assert_eq!(server.scheme, None);
+        assert_eq!(server.tag_prefix, "server-");
+        let client = &config.projects["client"];
+        assert_eq!(client.scheme, Some(VersionScheme::Semver));
+        assert_eq!(client.tag_prefix, "client@");
+        let tools = &config.projects["tools"];
+        assert_eq!(
+            tools.manifest_path,
+            Some(PathBuf::from("tools/pyproject.toml"))
+        );
+        assert_eq!(tools.selector.as_deref(), Some("tool.poetry.version"));
+
+        let rendered = render(&config, ConfigFormat::Yaml).unwrap();
+        let settings: crate::settings::Settings = serde_yaml::from_str(&rendered).unwrap();
+        assert_eq!(settings.projects.len(), 3);
+        let rendered = render(&config, ConfigFormat::Toml).unwrap();
+        assert!(rendered.contains("[projects.tools]"));
+    }
+
+    #[test]
+    fn test_detect_tag_prefix() {
+        let tags = [
+            "api/v1.0.0",
+            "api/v1.1.0",
+            "api-1.0.0",
+            "apis-1.0.0",
+            "v2.0.0",
+        ]
+        .map(str::to_string);
+        assert_eq!(
+            detect_tag_prefix("api", &tags, false).as_deref(),
+            Some("api/v")
+        );
+        assert_eq!(detect_tag_prefix("web", &tags, false), None);
+        assert_eq!(detect_tag_prefix("web", &tags, true).as_deref(), Some("v"));
+    }
+
+    #[test]
+    fn test_project_names() {
+        let paths = [
+            PathBuf::from("apps/api"),
+            PathBuf::from("libs/api"),
+            PathBuf::from("web"),
+        ];
+        let names = project_names(paths.iter(), Path::new("."));
+        assert_eq!(names[&paths[0]], "apps-api");
+        assert_eq!(names[&paths[1]], "libs-api");
+        assert_eq!(names[&paths[2]], "web");
+    }
+}
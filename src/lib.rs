@@ -0,0 +1,51 @@
+//! Version management for monorepos
+//!
+//! Projects are configured in [`Settings`] and released with [`release()`],
+//! which bumps the version in the project manifest, updates its dependents
+//! and changelog, and commits, tags and pushes the result as requested.
+//!
+//! ```no_run
+//! use monoverse::{projects::ReleaseOptions, Settings};
+//!
+//! let repo_path = std::path::Path::new(".");
+//! let settings = Settings::new(repo_path, None)?;
+//! let options = ReleaseOptions {
+//!     commit: true,
+//!     tag: true,
+//!     ..Default::default()
+//! };
+//! let report = monoverse::release(repo_path, &settings, "api", &options)?;
+//! if let Some(version) = report.version {
+//!     println!("Released api {}", version);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+mod changelog;
+mod conventional;
+pub mod dependents;
+mod edit;
+mod git;
+mod group;
+pub mod hooks;
+mod ignore;
+pub mod init;
+mod io;
+pub mod notes;
+pub mod projects;
+pub mod release;
+pub mod rollback;
+pub mod settings;
+mod sign;
+pub mod staging;
+mod template;
+#[cfg(test)]
+mod testing;
+pub mod validate;
+pub mod version;
+
+pub use dependents::Dependent;
+pub use projects::ProjectFile;
+pub use release::{release, ReleaseReport};
+pub use settings::Settings;
+pub use version::Version;
@@ -1,19 +1,13 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use clap::Parser;
 
 mod cli;
-mod dependents;
-mod edit;
-mod git;
-mod io;
-mod projects;
-mod settings;
-mod version;
 
 use cli::Opts;
 use git2::Repository;
+use monoverse::{init, notes, projects, release, rollback, settings, validate};
 
 fn main() {
     if let Err(e) = run() {
@@ -32,69 +26,219 @@ fn run() -> Result<()> {
         .verbosity(opts.verbosity.log_level_filter())
         .init()
         .unwrap();
-    let settings = settings::Settings::new(opts.repo_path.as_ref().unwrap())?;
-    log::info!("Settings: {:?}", settings);
+    let config = opts.config.as_deref();
+    let repo_path = || find_repo_path(opts.repo_path.as_deref());
     match opts.subcmd {
         cli::SubCommand::Release(release) => {
-            handle_release(release, settings, opts.repo_path.unwrap())?;
+            let repo_path = repo_path()?;
+            handle_release(release, load_settings(&repo_path, config)?, repo_path)?;
+        }
+        cli::SubCommand::Set(set) => {
+            let repo_path = repo_path()?;
+            handle_set(set, load_settings(&repo_path, config)?, repo_path)?;
         }
         cli::SubCommand::Next(next) => {
-            handle_next(next, settings, opts.repo_path.unwrap())?;
+            let repo_path = repo_path()?;
+            handle_next(next, load_settings(&repo_path, config)?, repo_path)?;
+        }
+        cli::SubCommand::Notes(notes) => {
+            let repo_path = repo_path()?;
+            handle_notes(notes, load_settings(&repo_path, config)?, repo_path)?;
+        }
+        cli::SubCommand::Rollback(rollback) => {
+            let repo_path = repo_path()?;
+            handle_rollback(rollback, load_settings(&repo_path, config)?, repo_path)?;
+        }
+        cli::SubCommand::Init(init) => {
+            handle_init(init, config, repo_path()?)?;
+        }
+        cli::SubCommand::Schema => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&settings::Settings::json_schema())?
+            );
+        }
+        cli::SubCommand::Validate => {
+            let repo_path = repo_path()?;
+            handle_validate(load_settings(&repo_path, config)?, repo_path)?;
         }
     }
     Ok(())
 }
 
+/// Return the root of the repository
+///
+/// Without `--repo-path`, the repository is discovered from the current
+/// directory upwards.
+fn find_repo_path(repo_path: Option<&Path>) -> Result<PathBuf> {
+    if let Some(repo_path) = repo_path {
+        return Ok(repo_path.to_path_buf());
+    }
+    let repo = Repository::discover(".")
+        .context("Could not find a git repository in the current directory or its parents")?;
+    repo.workdir()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| anyhow::anyhow!("The repository has no working directory"))
+}
+
+fn load_settings(repo_path: &Path, config: Option<&Path>) -> Result<settings::Settings> {
+    let settings = settings::Settings::new(repo_path, config)?;
+    log::info!("Settings: {:?}", settings);
+    Ok(settings)
+}
+
 fn handle_release(
     release: cli::Release,
     settings: settings::Settings,
     repo_path: PathBuf,
 ) -> Result<()> {
+    let options = release.update.options(release.force);
+    let report = release::release(&repo_path, &settings, &release.project, &options)?;
+    if let Some(version) = report.version {
+        println!("{}", version);
+    }
+    Ok(())
+}
+
+fn handle_set(set: cli::Set, settings: settings::Settings, repo_path: PathBuf) -> Result<()> {
+    let options = set.update.options(false);
+    let report = release::set_version(&repo_path, &settings, &set.project, &set.version, &options)?;
+    if let Some(version) = report.version {
+        println!("{}", version);
+    }
+    Ok(())
+}
+
+fn handle_next(next: cli::Next, settings: settings::Settings, repo_path: PathBuf) -> Result<()> {
+    println!(
+        "{}",
+        release::next_version(&repo_path, &settings, &next.project)?
+    );
+    Ok(())
+}
+
+fn handle_notes(notes: cli::Notes, settings: settings::Settings, repo_path: PathBuf) -> Result<()> {
     let repo = Repository::open(&repo_path)?;
-    let project_settings = settings.project_settings(&release.project)?;
-    let project_file = projects::get_project_file(project_settings.clone(), repo_path.clone());
-    let dependents = project_settings
-        .dependents
-        .iter()
-        .flatten()
-        .map(|dependent| dependents::get_dependent(dependent, repo_path.clone()))
-        .collect::<Result<Vec<_>>>()?;
-    let dependent_options = dependents::DependentUpdateOptions {
-        helm_dependency_update: release.helm_dependency_update,
+    let project_settings = settings.project_settings(&notes.project)?;
+    let template = match (&notes.template, &project_settings.notes_template) {
+        (Some(template), _) => std::fs::read_to_string(template)
+            .with_context(|| format!("Could not read file at: {}", template.display()))?,
+        (None, Some(template)) => std::fs::read_to_string(repo_path.join(template))
+            .with_context(|| format!("Could not read file at: {}", template.display()))?,
+        (None, None) => notes::DEFAULT_TEMPLATE.to_string(),
     };
-    if let Some(version) = project_file
-        .release(&repo, release.force)
-        .with_context(|| format!("Failed to release '{}'", release.project))?
-    {
-        let mut file_paths = Vec::new();
-        for dependent in dependents {
-            let mut dependent_paths = dependent.update_version(&version, &dependent_options)?;
-            file_paths.append(&mut dependent_paths);
-        }
-        if release.commit {
-            file_paths.push(project_file.get_manifest_file_path()?);
-            let commit_id = git::commit_files(
+    let project_file = projects::get_project_file(project_settings.clone(), repo_path);
+    let rendered = notes::render(
+        &repo,
+        &notes.project,
+        project_file.as_ref(),
+        notes.from.as_deref(),
+        notes.to.as_deref(),
+        &template,
+    )?;
+    println!("{}", rendered.trim_end());
+    Ok(())
+}
+
+fn handle_rollback(
+    rollback: cli::Rollback,
+    settings: settings::Settings,
+    repo_path: PathBuf,
+) -> Result<()> {
+    let repo = Repository::open(&repo_path)?;
+    let mode = match rollback.revert {
+        true => rollback::RollbackMode::Revert,
+        false => rollback::RollbackMode::Reset,
+    };
+    let report = match settings.groups.contains_key(&rollback.project) {
+        true => rollback::rollback_group(&repo, &rollback.project, &settings, &repo_path, mode),
+        false => {
+            let project_settings = settings.project_settings(&rollback.project)?;
+            let project_file = projects::get_project_file(project_settings.clone(), repo_path);
+            rollback::rollback(
                 &repo,
-                &file_paths,
-                &format!("chore: release {} {}", release.project, version),
-            )?;
-            if release.tag {
-                let tag_prefix = project_settings
-                    .tag_prefix
-                    .clone()
-                    .unwrap_or_else(|| format!("{}-", release.project));
-                let tag = format!("{}{}", tag_prefix, version);
-                git::tag_commit(&repo, commit_id, &tag)?;
-            }
+                &rollback.project,
+                &settings,
+                project_file.as_ref(),
+                mode,
+            )
         }
-        println!("{}", version);
     }
+    .with_context(|| format!("Failed to roll back '{}'", rollback.project))?;
+    for tag in &report.deleted_tags {
+        println!("Deleted tag {}", tag);
+    }
+    println!(
+        "Rolled back {} {} from commit {}, HEAD is now at {}",
+        rollback.project, report.version, report.release_id, report.head_id
+    );
     Ok(())
 }
 
-fn handle_next(next: cli::Next, settings: settings::Settings, repo_path: PathBuf) -> Result<()> {
-    let project_settings = settings.project_settings(&next.project)?;
-    let project_file = projects::get_project_file(project_settings.clone(), repo_path);
-    project_file.print_next_version()?;
+fn handle_init(init: cli::Init, config: Option<&Path>, repo_path: PathBuf) -> Result<()> {
+    let repo = Repository::open(&repo_path)?;
+    let format = init::ConfigFormat::from(init.format);
+    let config_path = match config {
+        Some(config) => config.to_path_buf(),
+        None => repo_path.join(format!("monoverse.{}", format.extension())),
+    };
+    let existing = match config {
+        Some(config) => config.exists().then(|| config.to_path_buf()),
+        None => settings::Settings::config_file(&repo_path),
+    };
+    if !init.dry_run && !init.force {
+        if let Some(existing) = &existing {
+            return Err(anyhow::anyhow!(
+                "Configuration file {} already exists, use --force to overwrite it",
+                existing.display()
+            ));
+        }
+    }
+    let config = init::scan(&repo, &repo_path)?;
+    if config.projects.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No projects found in {}",
+            repo_path.display()
+        ));
+    }
+    let rendered = init::render(&config, format)?;
+    if init.dry_run {
+        print!("{}", rendered);
+        return Ok(());
+    }
+    std::fs::write(&config_path, rendered)
+        .with_context(|| format!("Could not write file at: {}", config_path.display()))?;
+    println!(
+        "Wrote {} with {} projects: {}",
+        config_path.display(),
+        config.projects.len(),
+        config
+            .projects
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    // Only one configuration file is read, so remove one with another extension
+    if let Some(existing) = existing.filter(|existing| existing != &config_path) {
+        std::fs::remove_file(&existing)
+            .with_context(|| format!("Could not remove file at: {}", existing.display()))?;
+        println!("Removed {}", existing.display());
+    }
     Ok(())
 }
+
+fn handle_validate(settings: settings::Settings, repo_path: PathBuf) -> Result<()> {
+    let problems = validate::validate(&settings, &repo_path);
+    if problems.is_empty() {
+        println!("Configuration is valid");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("{}", problem);
+    }
+    Err(anyhow::anyhow!(
+        "Found {} problems in the configuration",
+        problems.len()
+    ))
+}
@@ -0,0 +1,217 @@
+use anyhow::Result;
+use git2::{Oid, Repository};
+
+use crate::{changelog, git, projects::ProjectFile, template::TemplateContext};
+
+/// Release notes template used when neither the project nor the CLI define one
+pub const DEFAULT_TEMPLATE: &str = "## {project} {version}\n\n{changes}";
+
+/// Render release notes for the commits that touched the project path
+///
+/// `from` and `to` are either versions, which are resolved to tags with the
+/// project tag format, or git revisions. `to` defaults to HEAD and `from` to
+/// the release before `to`. If `to` is a release commit, it is left out of
+/// the notes. Without a release before `to`, the notes cover all history.
+pub fn render(
+    repo: &Repository,
+    project: &str,
+    project_file: &dyn ProjectFile,
+    from: Option<&str>,
+    to: Option<&str>,
+    template: &str,
+) -> Result<String> {
+    let settings = &project_file.base().settings;
+    let manifest_path = project_file.get_manifest_file_path()?;
+    let to_id = match to {
+        Some(to) => resolve(repo, project, project_file, to)?,
+        None => repo.head()?.peel_to_commit()?.id(),
+    };
+    let version_context =
+        project_file.version_context(&git::read_file_at(repo, &manifest_path, to_id)?)?;
+    let to_release =
+        git::get_commit_id_for_line_at(repo, &manifest_path, version_context.line_number, to_id)?;
+    let from_id = match from {
+        Some(from) => Some(resolve(repo, project, project_file, from)?),
+        None if to_release == to_id => previous_release(repo, project_file, to_id)?,
+        None => Some(to_release),
+    };
+    let commits = git::commits_touching_path(repo, &settings.project_path, from_id, to_id)?;
+    let commits = settings.ignore_rules()?.filter(commits)?;
+    let commits = commits
+        .iter()
+        .filter(|commit| commit.id() != to_release)
+        .collect::<Vec<_>>();
+    let messages = commits
+        .iter()
+        .map(|commit| commit.message().unwrap_or_default())
+        .collect::<Vec<_>>();
+    let commit_list = commits
+        .iter()
+        .map(|commit| {
+            format!(
+                "- {} ({})",
+                commit.summary().unwrap_or_default(),
+                short_id(commit.id())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    TemplateContext::new()
+        .with("project", project)
+        .with("version", &version_context.version)
+        .with(
+            "from",
+            from.map(str::to_string)
+                .or(from_id.map(short_id))
+                .unwrap_or_default(),
+        )
+        .with("to", to.map(str::to_string).unwrap_or(short_id(to_id)))
+        .with("date", chrono::Utc::now().format("%Y-%m-%d"))
+        .with("changes", changelog::render_changes(&messages))
+        .with("commits", commit_list)
+        .render(template)
+}
+
+/// Resolve a version or revision to a commit
+///
+/// A version is looked up as the tag the project tag format produces for
+/// it, anything else as a git revision.
+fn resolve(
+    repo: &Repository,
+    project: &str,
+    project_file: &dyn ProjectFile,
+    value: &str,
+) -> Result<Oid> {
+    let settings = &project_file.base().settings;
+    let tag = TemplateContext::new()
+        .with("project", project)
+        .with("prefix", settings.tag_prefix_for(project))
+        .with("version", value)
+        .render(settings.tag_format_template());
+    if let Ok(tag) = tag {
+        if let Ok(commit_id) = git::resolve_commit(repo, &format!("refs/tags/{}", tag)) {
+            return Ok(commit_id);
+        }
+    }
+    git::resolve_commit(repo, value)
+}
+
+/// Return the release commit before the given release commit
+///
+/// None if there is no earlier release, because the release commit has no
+/// parent or the manifest did not exist before it.
+fn previous_release(
+    repo: &Repository,
+    project_file: &dyn ProjectFile,
+    release_id: Oid,
+) -> Result<Option<Oid>> {
+    let manifest_path = project_file.get_manifest_file_path()?;
+    let Ok(parent) = repo.find_commit(release_id)?.parent(0) else {
+        return Ok(None);
+    };
+    if parent.tree()?.get_path(&manifest_path).is_err() {
+        return Ok(None);
+    }
+    let version_context =
+        project_file.version_context(&git::read_file_at(repo, &manifest_path, parent.id())?)?;
+    Ok(Some(git::get_commit_id_for_line_at(
+        repo,
+        &manifest_path,
+        version_context.line_number,
+        parent.id(),
+    )?))
+}
+
+fn short_id(commit_id: Oid) -> String {
+    commit_id.to_string()[..7].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        projects::get_project_file,
+        settings::ProjectSettings,
+        testing::{commit_all, init_repo, initial_commit, write},
+    };
+
+    const TEMPLATE: &str = "{project} {version} ({from}..{to})\n\n{changes}";
+
+    fn project_settings() -> ProjectSettings {
+        serde_yaml::from_str("type: versionfile\npath: app\nmanifest_path: app/VERSION").unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "25.1.0\n");
+        initial_commit(&repo, &["app/VERSION"]);
+        write(dir.path(), "app/main.rs", "fn main() {}");
+        commit_all(&repo, "feat: add main");
+        write(dir.path(), "app/VERSION", "25.1.1\n");
+        let release_id = commit_all(&repo, "chore: release app 25.1.1");
+        repo.tag_lightweight(
+            "app-25.1.1",
+            &repo.find_object(release_id, None).unwrap(),
+            false,
+        )
+        .unwrap();
+        write(dir.path(), "app/main.rs", "fn main() { }");
+        commit_all(&repo, "fix: format main");
+        write(dir.path(), "other.txt", "unrelated");
+        commit_all(&repo, "feat: unrelated");
+
+        let project_file = get_project_file(project_settings(), dir.path().to_path_buf());
+        let notes = render(
+            &repo,
+            "app",
+            project_file.as_ref(),
+            None,
+            Some("25.1.1"),
+            TEMPLATE,
+        )
+        .unwrap();
+        assert!(notes.starts_with("app 25.1.1 ("));
+        assert!(notes.ends_with("..25.1.1)\n\n### Added\n\n- add main\n"));
+
+        let notes = render(
+            &repo,
+            "app",
+            project_file.as_ref(),
+            Some("app-25.1.1"),
+            None,
+            TEMPLATE,
+        )
+        .unwrap();
+        assert!(notes.starts_with("app 25.1.1 (app-25.1.1.."));
+        assert!(notes.ends_with(")\n\n### Fixed\n\n- format main\n"));
+    }
+
+    #[test]
+    fn test_render_first_release() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "README.md", "readme");
+        initial_commit(&repo, &["README.md"]);
+        write(dir.path(), "app/main.rs", "fn main() {}");
+        commit_all(&repo, "feat: add main");
+        write(dir.path(), "app/lib.rs", "");
+        commit_all(&repo, "feat: add lib [skip release]");
+        write(dir.path(), "app/VERSION", "25.1.0\n");
+        commit_all(&repo, "chore: release app 25.1.0");
+
+        let mut settings = project_settings();
+        settings.ignore = serde_yaml::from_str(r"[{message: '\[skip release\]'}]").unwrap();
+        let project_file = get_project_file(settings, dir.path().to_path_buf());
+        let notes = render(&repo, "app", project_file.as_ref(), None, None, TEMPLATE).unwrap();
+        assert!(notes.starts_with("app 25.1.0 (.."));
+        assert!(notes.ends_with(")\n\n### Added\n\n- add main\n"));
+
+        // The first release is also the root commit
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "25.1.0\n");
+        initial_commit(&repo, &["app/VERSION"]);
+        let project_file = get_project_file(project_settings(), dir.path().to_path_buf());
+        let notes = render(&repo, "app", project_file.as_ref(), None, None, TEMPLATE).unwrap();
+        assert!(notes.starts_with("app 25.1.0 (.."));
+    }
+}
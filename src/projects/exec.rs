@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::settings::ProjectSettings;
+use crate::version::{ToVersion, VersionContext};
+
+/// Project whose manifest is read and written by an external executable
+pub struct ExecProject {
+    base: super::BaseProjectFile,
+}
+
+impl ExecProject {
+    pub fn new(settings: ProjectSettings, repo_path: PathBuf) -> Self {
+        Self {
+            base: super::BaseProjectFile {
+                settings,
+                repo_path,
+            },
+        }
+    }
+
+    fn command(&self) -> Result<&str> {
+        self.base
+            .settings
+            .command
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("Command is required for an exec project"))
+    }
+}
+
+impl super::ProjectFile for ExecProject {
+    fn base(&self) -> &super::BaseProjectFile {
+        &self.base
+    }
+
+    fn update_version(
+        &self,
+        version_file_content: &str,
+        version_context: &VersionContext,
+    ) -> Result<String> {
+        crate::edit::exec::edit(
+            self.command()?,
+            &self.base.repo_path,
+            &self.get_manifest_file_path()?,
+            version_file_content,
+            self.base.settings.selector.as_deref(),
+            &version_context.next_version.to_string(),
+        )
+    }
+
+    fn version_context(&self, version_file_content: &str) -> Result<VersionContext> {
+        let version_line = crate::edit::exec::query(
+            self.command()?,
+            &self.base.repo_path,
+            &self.get_manifest_file_path()?,
+            version_file_content,
+            self.base.settings.selector.as_deref(),
+        )?;
+        Ok(VersionContext::new(
+            version_line.value.to_version(),
+            version_line.line_number,
+        ))
+    }
+}
@@ -62,9 +62,21 @@ mod tests {
                 project_type: crate::projects::ProjectType::Helm,
                 project_path: PathBuf::new(),
                 manifest_path: Some("Chart.yaml".into()),
+                scheme: Default::default(),
                 tag_prefix: None,
+                tag_format: None,
+                tag_message: None,
+                tag_type: Default::default(),
+                commit_message: None,
+                remote: None,
+                changelog: None,
+                notes_template: None,
                 selector: None,
+                command: None,
                 dependents: None,
+                dirty_check: Default::default(),
+                ignore: None,
+                hooks: Default::default(),
             },
             PathBuf::new(),
         );
@@ -85,9 +97,21 @@ version: 0.1.0"#;
                 project_type: crate::projects::ProjectType::Helm,
                 project_path: PathBuf::new(),
                 manifest_path: Some("Chart.yaml".into()),
+                scheme: Default::default(),
                 tag_prefix: None,
+                tag_format: None,
+                tag_message: None,
+                tag_type: Default::default(),
+                commit_message: None,
+                remote: None,
+                changelog: None,
+                notes_template: None,
                 selector: None,
+                command: None,
                 dependents: None,
+                dirty_check: Default::default(),
+                ignore: None,
+                hooks: Default::default(),
             },
             PathBuf::new(),
         );
@@ -1,15 +1,18 @@
 use std::path::PathBuf;
 
-use anyhow::Result;
-use git2::Repository;
-use serde::Deserialize;
+use anyhow::{Context, Result};
+use git2::{Commit, Oid, Repository};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    git,
-    settings::ProjectSettings,
+    conventional, git,
+    settings::{DirtyCheck, ProjectSettings, VersionScheme},
+    staging::Staging,
     version::{Version, VersionContext},
 };
 
+pub mod exec;
 pub mod helm;
 pub mod node;
 pub mod rust;
@@ -17,17 +20,59 @@ pub mod toml;
 pub mod versionfile;
 pub mod yaml;
 
-#[derive(Deserialize, Debug, Clone)]
+/// The kind of manifest a project keeps its version in
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum ProjectType {
+    /// Any file, the version is queried and edited by `command`
+    Exec,
+    /// Helm chart, the version is `appVersion` in `Chart.yaml`
     Helm,
+    /// Node.js package, the version is in `package.json`
     Node,
+    /// Rust crate, the version is in `Cargo.toml`
     Rust,
+    /// TOML file with the version at `selector`
     Toml,
+    /// Plain text file that only contains the version
     Versionfile,
+    /// YAML file with the version at `selector`
     Yaml,
 }
 
+/// Options of a release
+///
+/// Only `force` and `allow_dirty` apply to the manifest update, the rest
+/// control how the release is published.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReleaseOptions {
+    /// Release even if the project has no changes
+    pub force: bool,
+    /// Release even if the project has uncommitted changes
+    pub allow_dirty: bool,
+    /// Commit the changes
+    pub commit: bool,
+    /// Tag the release commit
+    pub tag: bool,
+    /// Push the branch and the tag
+    pub push: bool,
+    /// Sign the commit and the tag even if git is not configured to
+    pub sign: bool,
+    /// Run `helm dependency update` for helm dependents
+    pub helm_dependency_update: bool,
+}
+
+/// Result of a project release
+#[derive(Debug, Clone)]
+pub struct ReleaseOutcome {
+    pub version_context: VersionContext,
+    /// The commit that last changed the version line before this release
+    ///
+    /// None if it cannot be determined, which is only possible for forced
+    /// releases.
+    pub previous_release: Option<Oid>,
+}
+
 pub struct BaseProjectFile {
     pub settings: ProjectSettings,
     pub repo_path: PathBuf,
@@ -35,6 +80,7 @@ pub struct BaseProjectFile {
 
 pub fn get_project_file(settings: ProjectSettings, repo_path: PathBuf) -> Box<dyn ProjectFile> {
     match settings.project_type {
+        ProjectType::Exec => Box::new(exec::ExecProject::new(settings, repo_path)),
         ProjectType::Helm => Box::new(helm::HelmProject::new(settings, repo_path)),
         ProjectType::Node => Box::new(node::NodeProject::new(settings, repo_path)),
         ProjectType::Rust => Box::new(rust::RustProject::new(settings, repo_path)),
@@ -60,44 +106,54 @@ pub trait ProjectFile {
     /// Release project
     ///
     /// If the project has changed since the last release, update the version
-    /// and write it to the manifest file. Return the new version.
+    /// and stage the new manifest. Return the version context with the
+    /// previous and the new version, and the previous release commit.
     ///
     /// If the project has not changed since the last release, return None.
-    fn release(&self, repo: &Repository, force: bool) -> Result<Option<Version>> {
-        let version_file_path = self.get_manifest_file_path()?;
-        let version_file_status = repo.status_file(&version_file_path)?;
-        if version_file_status.is_wt_modified() || version_file_status.is_index_modified() {
-            return Err(anyhow::anyhow!(
-                "The version file '{}' has been modified. Please stash or commit your changes before releasing.",
-                version_file_path.display()
-            ));
+    fn release(
+        &self,
+        repo: &Repository,
+        options: &ReleaseOptions,
+        staging: &mut Staging,
+    ) -> Result<Option<ReleaseOutcome>> {
+        if !options.allow_dirty {
+            self.ensure_clean(repo)?;
         }
-        let version_file_content =
-            crate::io::read_file(&version_file_path, &self.base().repo_path)?;
+        let version_file_path = self.get_manifest_file_path()?;
+        let version_file_content = staging.read(&version_file_path)?;
         let version_context = self.version_context(&version_file_content)?;
 
-        let do_release = match force {
-            true => true,
+        let previous_release =
+            git::get_commit_id_for_line(repo, &version_file_path, version_context.line_number);
+        let (do_release, previous_release) = match options.force {
+            true => (true, previous_release.ok()),
             false => {
-                let commit_id = git::get_commit_id_for_line(
-                    repo,
-                    &version_file_path,
-                    version_context.line_number,
-                )?;
-                git::has_path_changed_since(repo, &self.base().settings.project_path, commit_id)?
+                let commit_id = previous_release?;
+                let changed = !self.release_commits(repo, commit_id)?.is_empty();
+                (changed, Some(commit_id))
             }
         };
+        let mut version_context = version_context;
+        if do_release {
+            match self.next_version(repo, &version_context.version, previous_release)? {
+                Some(next_version) => version_context.next_version = next_version,
+                None if options.force => {
+                    version_context.next_version = version_context.version.bump_patch()
+                }
+                None => {
+                    log::info!("There are only changes that do not require a release.");
+                    return Ok(None);
+                }
+            }
+        }
         match do_release {
             true => {
                 log::info!("There are changes to the project.");
-                let new_version_file =
-                    self.update_version(&version_file_content, &version_context)?;
-                crate::io::write_file(
-                    &version_file_path,
-                    &self.base().repo_path,
-                    new_version_file.as_str(),
-                )?;
-                Ok(Some(version_context.next_version))
+                self.write_version(&version_file_content, &version_context, staging)?;
+                Ok(Some(ReleaseOutcome {
+                    version_context,
+                    previous_release,
+                }))
             }
             false => {
                 log::info!("There are no changes to the project.");
@@ -106,14 +162,153 @@ pub trait ProjectFile {
         }
     }
 
-    /// Print the next version for the project
-    fn print_next_version(&self) -> Result<()> {
+    /// Set the project version, bypassing change detection
+    ///
+    /// Fails if the manifest already has the given version.
+    fn set_version(
+        &self,
+        repo: &Repository,
+        version: &Version,
+        allow_dirty: bool,
+        staging: &mut Staging,
+    ) -> Result<ReleaseOutcome> {
+        if !allow_dirty {
+            self.ensure_clean(repo)?;
+        }
+        let version_file_path = self.get_manifest_file_path()?;
+        let version_file_content = staging.read(&version_file_path)?;
+        let mut version_context = self.version_context(&version_file_content)?;
+        if &version_context.version == version {
+            return Err(anyhow::anyhow!("The version is already {}", version));
+        }
+        let previous_release =
+            git::get_commit_id_for_line(repo, &version_file_path, version_context.line_number).ok();
+        version_context.next_version = version.clone();
+        self.write_version(&version_file_content, &version_context, staging)?;
+        Ok(ReleaseOutcome {
+            version_context,
+            previous_release,
+        })
+    }
+
+    /// Stage the manifest file with the next version from the version context
+    ///
+    /// The staged manifest is read back to check that it has the next version.
+    fn write_version(
+        &self,
+        version_file_content: &str,
+        version_context: &VersionContext,
+        staging: &mut Staging,
+    ) -> Result<()> {
+        let manifest_path = self.get_manifest_file_path()?;
+        let new_version_file = self.update_version(version_file_content, version_context)?;
+        let written = self.version_context(&new_version_file).with_context(|| {
+            format!(
+                "Could not read the version back from {}",
+                manifest_path.display()
+            )
+        })?;
+        if written.version != version_context.next_version {
+            return Err(anyhow::anyhow!(
+                "Updating {} produced version {} instead of {}",
+                manifest_path.display(),
+                written.version,
+                version_context.next_version
+            ));
+        }
+        staging.write(manifest_path, new_version_file);
+        Ok(())
+    }
+
+    /// Fail if the paths covered by the dirty check have uncommitted changes
+    ///
+    /// The error lists every offending file, including untracked ones.
+    fn ensure_clean(&self, repo: &Repository) -> Result<()> {
+        let settings = &self.base().settings;
+        if settings.dirty_check == DirtyCheck::None {
+            return Ok(());
+        }
+        let dirty_files = git::dirty_files(repo, &settings.dirty_check_paths()?)?;
+        if dirty_files.is_empty() {
+            return Ok(());
+        }
+        let report = dirty_files
+            .iter()
+            .map(|(path, status)| {
+                format!("  {} ({})", path.display(), git::describe_status(*status))
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Err(anyhow::anyhow!(
+            "The project has uncommitted changes. Please stash or commit your changes before releasing, or use --allow-dirty.\n{}",
+            report
+        ))
+    }
+
+    /// Return the commits since the previous release that count as changes
+    ///
+    /// These are the commits that touched the project path, excluding the
+    /// ones matching an ignore rule.
+    fn release_commits<'r>(
+        &self,
+        repo: &'r Repository,
+        previous_release: Oid,
+    ) -> Result<Vec<Commit<'r>>> {
+        let settings = &self.base().settings;
+        let head_id = repo.head()?.peel_to_commit()?.id();
+        let commits = git::commits_touching_path(
+            repo,
+            &settings.project_path,
+            Some(previous_release),
+            head_id,
+        )?;
+        settings.ignore_rules()?.filter(commits)
+    }
+
+    /// Compute the next version according to the project version scheme
+    ///
+    /// Calendar versions are bumped based on the current date. Semantic
+    /// versions are bumped based on the Conventional Commits since the
+    /// previous release, returning None if none of them require a release.
+    fn next_version(
+        &self,
+        repo: &Repository,
+        version: &Version,
+        previous_release: Option<Oid>,
+    ) -> Result<Option<Version>> {
+        let settings = &self.base().settings;
+        match settings.scheme {
+            VersionScheme::Calver => Ok(Some(version.bump())),
+            VersionScheme::Semver => {
+                let Some(previous_release) = previous_release else {
+                    return Ok(None);
+                };
+                let commits = self.release_commits(repo, previous_release)?;
+                let messages = commits
+                    .iter()
+                    .map(|commit| commit.message().unwrap_or_default())
+                    .collect::<Vec<_>>();
+                let bump = conventional::bump_for(&messages);
+                log::info!("Bump level from commit history: {:?}", bump);
+                Ok(bump.map(|bump| version.bump_semver(bump)))
+            }
+        }
+    }
+
+    /// Return the next version for the project without editing files
+    ///
+    /// Semantic versions without releasable changes get a patch bump, which
+    /// is what a forced release would produce.
+    fn upcoming_version(&self, repo: &Repository) -> Result<Version> {
         let version_file_path = self.get_manifest_file_path()?;
         let version_file_content =
             crate::io::read_file(&version_file_path, &self.base().repo_path)?;
         let version_context = self.version_context(&version_file_content)?;
-        println!("{}", version_context.version.bump());
-        Ok(())
+        let previous_release =
+            git::get_commit_id_for_line(repo, &version_file_path, version_context.line_number).ok();
+        Ok(self
+            .next_version(repo, &version_context.version, previous_release)?
+            .unwrap_or_else(|| version_context.version.bump_patch()))
     }
 
     /// Get the path to the manifest file
@@ -121,3 +316,124 @@ pub trait ProjectFile {
         self.base().settings.get_manifest_file_path()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{commit_all, init_repo, initial_commit, write};
+    use crate::version::ToVersion;
+
+    fn semver_project(repo_path: &std::path::Path) -> Box<dyn ProjectFile> {
+        let settings: ProjectSettings = serde_yaml::from_str(
+            "type: versionfile\npath: app\nmanifest_path: app/VERSION\nscheme: semver",
+        )
+        .unwrap();
+        get_project_file(settings, repo_path.to_path_buf())
+    }
+
+    #[test]
+    fn test_release_semver() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "1.2.3");
+        initial_commit(&repo, &["app/VERSION"]);
+        write(dir.path(), "app/README.md", "docs");
+        commit_all(&repo, "docs: add readme");
+        let project = semver_project(dir.path());
+        let mut staging = Staging::new(dir.path());
+
+        let options = ReleaseOptions::default();
+        assert!(project
+            .release(&repo, &options, &mut staging)
+            .unwrap()
+            .is_none());
+
+        write(dir.path(), "app/main.rs", "fn main() {}");
+        commit_all(&repo, "feat: add main");
+        write(dir.path(), "app/lib.rs", "");
+        commit_all(&repo, "fix: add lib");
+        let outcome = project
+            .release(&repo, &options, &mut staging)
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.version_context.next_version, "1.3.0".to_version());
+        assert_eq!(staging.staged("app/VERSION"), Some("1.3.0"));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("app/VERSION")).unwrap(),
+            "1.2.3"
+        );
+    }
+
+    #[test]
+    fn test_release_ignored_commits() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "25.1.0");
+        initial_commit(&repo, &["app/VERSION"]);
+        write(dir.path(), "app/main.rs", "fn main() {}");
+        commit_all(&repo, "fix: add main [skip release]");
+        let settings: ProjectSettings = serde_yaml::from_str(
+            r#"
+type: versionfile
+path: app
+manifest_path: app/VERSION
+ignore:
+  - message: '\[skip release\]'
+"#,
+        )
+        .unwrap();
+        let project = get_project_file(settings, dir.path().to_path_buf());
+        let mut staging = Staging::new(dir.path());
+        let options = ReleaseOptions::default();
+        assert!(project
+            .release(&repo, &options, &mut staging)
+            .unwrap()
+            .is_none());
+
+        write(dir.path(), "app/main.rs", "fn main() { }");
+        commit_all(&repo, "fix: format main");
+        assert!(project
+            .release(&repo, &options, &mut staging)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_release_semver_forced() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "1.2.3");
+        initial_commit(&repo, &["app/VERSION"]);
+        write(dir.path(), "app/README.md", "docs");
+        commit_all(&repo, "chore: add readme");
+        let project = semver_project(dir.path());
+
+        let options = ReleaseOptions {
+            force: true,
+            ..Default::default()
+        };
+        let mut staging = Staging::new(dir.path());
+        let outcome = project
+            .release(&repo, &options, &mut staging)
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome.version_context.next_version, "1.2.4".to_version());
+    }
+
+    #[test]
+    fn test_set_version() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "1.2.3");
+        let initial_id = initial_commit(&repo, &["app/VERSION"]);
+        let project = semver_project(dir.path());
+        let mut staging = Staging::new(dir.path());
+
+        let outcome = project
+            .set_version(&repo, &"1.2.7".to_version(), false, &mut staging)
+            .unwrap();
+        assert_eq!(outcome.version_context.version, "1.2.3".to_version());
+        assert_eq!(outcome.version_context.next_version, "1.2.7".to_version());
+        assert_eq!(outcome.previous_release, Some(initial_id));
+        assert_eq!(staging.staged("app/VERSION"), Some("1.2.7"));
+        assert!(project
+            .set_version(&repo, &"1.2.7".to_version(), true, &mut staging)
+            .is_err());
+    }
+}
@@ -32,8 +32,10 @@ impl super::ProjectFile for RustProject {
         version_file_content: &str,
     ) -> anyhow::Result<crate::version::VersionContext> {
         let doc = version_file_content.parse::<Document>()?;
-        let version = doc["package"]["version"]
-            .as_str()
+        let version = doc
+            .get("package")
+            .and_then(|package| package.get("version"))
+            .and_then(|version| version.as_str())
             .ok_or(anyhow::anyhow!(
                 "Failed to parse version from Cargo.toml: {:?}",
                 self.base.settings.get_manifest_file_path()
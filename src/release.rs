@@ -0,0 +1,1038 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+
+use crate::{
+    changelog,
+    dependents::{self, Dependent},
+    git, group,
+    hooks::{self, Hooks},
+    projects::{self, ProjectFile, ReleaseOptions, ReleaseOutcome},
+    settings::{ProjectSettings, Settings, TagType},
+    sign,
+    staging::Staging,
+    template::TemplateContext,
+    version::{Version, VersionContext},
+};
+
+/// A project that was released
+#[derive(Debug, Clone)]
+pub struct ReleasedProject {
+    pub name: String,
+    /// The version before the release
+    pub previous_version: Version,
+    pub manifest_path: PathBuf,
+}
+
+/// Result of a release
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseReport {
+    /// The new version, None if there was nothing to release
+    pub version: Option<Version>,
+    /// The released projects, every member if a group was released
+    pub projects: Vec<ReleasedProject>,
+    /// Files written by the release: manifests, dependents and changelogs
+    pub files: Vec<PathBuf>,
+    /// The release commit, if one was created
+    pub commit_id: Option<Oid>,
+    /// The release tag, if one was created
+    pub tag: Option<String>,
+}
+
+/// Release a project or a group
+///
+/// The version is only bumped if the project has changed since its last
+/// release, unless `force` is set. Dependents and the changelog are updated
+/// with the manifest, and the changes committed, tagged and pushed as the
+/// options request.
+pub fn release(
+    repo_path: &Path,
+    settings: &Settings,
+    project: &str,
+    options: &ReleaseOptions,
+) -> Result<ReleaseReport> {
+    if settings.groups.contains_key(project) {
+        return release_group(repo_path, settings, project, options);
+    }
+    if let Some(group) = settings.group_of(project) {
+        return Err(anyhow::anyhow!(
+            "Project '{}' is a member of group '{}', release the group instead",
+            project,
+            group
+        ));
+    }
+    write_release(
+        repo_path,
+        settings,
+        project,
+        options,
+        |repo, project_file, staging| {
+            project_file
+                .release(repo, options, staging)
+                .with_context(|| format!("Failed to release '{}'", project))
+        },
+    )
+}
+
+/// Set the version of a project, bypassing change detection
+///
+/// Group members share one version, so their version cannot be set alone.
+pub fn set_version(
+    repo_path: &Path,
+    settings: &Settings,
+    project: &str,
+    version: &Version,
+    options: &ReleaseOptions,
+) -> Result<ReleaseReport> {
+    if let Some(group) = settings.group_of(project) {
+        return Err(anyhow::anyhow!(
+            "Project '{}' is a member of group '{}' and shares its version, release the group instead",
+            project,
+            group
+        ));
+    }
+    write_release(
+        repo_path,
+        settings,
+        project,
+        options,
+        |repo, project_file, staging| {
+            project_file
+                .set_version(repo, version, options.allow_dirty, staging)
+                .map(Some)
+                .with_context(|| format!("Failed to set the version of '{}'", project))
+        },
+    )
+}
+
+/// Return the next version of a project or a group without editing files
+pub fn next_version(repo_path: &Path, settings: &Settings, project: &str) -> Result<Version> {
+    let repo = Repository::open(repo_path)?;
+    if let Some(group) = settings.groups.get(project) {
+        return group::upcoming_version(&repo, group, settings, repo_path);
+    }
+    let project_settings = settings.project_settings(project)?;
+    let project_file =
+        projects::get_project_file(project_settings.clone(), repo_path.to_path_buf());
+    project_file.upcoming_version(&repo)
+}
+
+/// Write a new project version and bring the rest of the repository along
+///
+/// `write_version` stages the manifest and returns None if there is nothing
+/// to release. The dependents and the changelog are then staged, and the
+/// files written and published together.
+fn write_release<F>(
+    repo_path: &Path,
+    settings: &Settings,
+    project: &str,
+    options: &ReleaseOptions,
+    write_version: F,
+) -> Result<ReleaseReport>
+where
+    F: FnOnce(&Repository, &dyn ProjectFile, &mut Staging) -> Result<Option<ReleaseOutcome>>,
+{
+    let repo = Repository::open(repo_path)?;
+    let project_settings = settings.project_settings(project)?;
+    let project_file =
+        projects::get_project_file(project_settings.clone(), repo_path.to_path_buf());
+    let dependents = project_dependents(project_settings, repo_path)?;
+    let publisher = Publisher::new(&repo, options)?;
+    let mut staging = Staging::new(repo_path);
+    let Some(outcome) = write_version(&repo, project_file.as_ref(), &mut staging)? else {
+        return Ok(ReleaseReport::default());
+    };
+    let version_context = outcome.version_context;
+    let version = version_context.next_version.clone();
+    let dependent_paths = update_dependents(
+        &repo,
+        project_settings,
+        &dependents,
+        &version,
+        outcome.previous_release,
+        options,
+        &mut staging,
+    )?;
+    let publication = match options.commit {
+        true => {
+            let context =
+                release_template_context(project, project_settings, &version_context, &staging)
+                    .with("dependents", join_paths(&dependent_paths));
+            Some(Publication::render(
+                context,
+                options,
+                settings.commit_message_template(project_settings),
+                project_settings.tag_format_template(),
+                match project_settings.tag_type {
+                    TagType::Annotated => Some(project_settings.tag_message_template()),
+                    TagType::Lightweight => None,
+                },
+                settings.remote(project_settings),
+            )?)
+        }
+        false => None,
+    };
+    let mut report = ReleaseReport {
+        version: Some(version),
+        projects: vec![ReleasedProject {
+            name: project.to_string(),
+            previous_version: version_context.version.clone(),
+            manifest_path: project_file.get_manifest_file_path()?,
+        }],
+        ..Default::default()
+    };
+    let hooks = [Hooks::new(
+        project,
+        project_settings,
+        repo_path,
+        &version_context.next_version,
+        &version_context.version,
+        release_tag(&publication),
+    )?];
+    publisher.write_and_publish(
+        &repo,
+        staging,
+        &dependents,
+        &hooks,
+        publication,
+        &mut report,
+    )?;
+    Ok(report)
+}
+
+/// Release every member of a group with one commit and a single group tag
+fn release_group(
+    repo_path: &Path,
+    settings: &Settings,
+    name: &str,
+    options: &ReleaseOptions,
+) -> Result<ReleaseReport> {
+    let repo = Repository::open(repo_path)?;
+    let group = settings.group_settings(name)?;
+    let dependents = group
+        .members
+        .iter()
+        .map(|member| project_dependents(settings.project_settings(member)?, repo_path))
+        .collect::<Result<Vec<_>>>()?;
+    let publisher = Publisher::new(&repo, options)?;
+    let mut staging = Staging::new(repo_path);
+    let Some(outcome) = group::release(&repo, group, settings, repo_path, options, &mut staging)
+        .with_context(|| format!("Failed to release group '{}'", name))?
+    else {
+        return Ok(ReleaseReport::default());
+    };
+    let mut report = ReleaseReport {
+        version: Some(outcome.version.clone()),
+        ..Default::default()
+    };
+    let mut dependent_paths = Vec::new();
+    for (member, dependents) in outcome.members.iter().zip(&dependents) {
+        dependent_paths.extend(update_dependents(
+            &repo,
+            &member.project_file.base().settings,
+            dependents,
+            &outcome.version,
+            member.outcome.previous_release,
+            options,
+            &mut staging,
+        )?);
+        report.projects.push(ReleasedProject {
+            name: member.name.clone(),
+            previous_version: member.outcome.version_context.version.clone(),
+            manifest_path: member.project_file.get_manifest_file_path()?,
+        });
+    }
+    let publication = match options.commit {
+        true => {
+            let context = TemplateContext::new()
+                .with("project", name)
+                .with("prefix", group.tag_prefix_for(name))
+                .with("version", &outcome.version)
+                .with("previous_version", &outcome.previous_version)
+                .with("date", chrono::Utc::now().format("%Y-%m-%d"))
+                .with("changelog", "")
+                .with(
+                    "members",
+                    outcome
+                        .members
+                        .iter()
+                        .map(|member| member.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                )
+                .with("dependents", join_paths(&dependent_paths));
+            Some(Publication::render(
+                context,
+                options,
+                group.commit_message_template(settings),
+                group.tag_format_template(),
+                match group.tag_type {
+                    TagType::Annotated => Some(group.tag_message_template()),
+                    TagType::Lightweight => None,
+                },
+                group.remote(settings),
+            )?)
+        }
+        false => None,
+    };
+    let hooks = outcome
+        .members
+        .iter()
+        .map(|member| {
+            Hooks::new(
+                &member.name,
+                &member.project_file.base().settings,
+                repo_path,
+                &outcome.version,
+                &member.outcome.version_context.version,
+                release_tag(&publication),
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+    publisher.write_and_publish(
+        &repo,
+        staging,
+        &dependents.into_iter().flatten().collect::<Vec<_>>(),
+        &hooks,
+        publication,
+        &mut report,
+    )?;
+    Ok(report)
+}
+
+/// Build the template context for release commit messages and tags
+fn release_template_context(
+    project: &str,
+    project_settings: &ProjectSettings,
+    version_context: &VersionContext,
+    staging: &Staging,
+) -> TemplateContext {
+    // Only the changelog that monoverse manages is quoted, the same one
+    // `changelog::update` writes
+    let changelog = project_settings
+        .changelog
+        .as_ref()
+        .and_then(|path| staging.read(path).ok())
+        .and_then(|content| changelog::excerpt(&content, &version_context.next_version))
+        .unwrap_or_default();
+    TemplateContext::new()
+        .with("project", project)
+        .with("prefix", project_settings.tag_prefix_for(project))
+        .with("version", &version_context.next_version)
+        .with("previous_version", &version_context.version)
+        .with("date", chrono::Utc::now().format("%Y-%m-%d"))
+        .with("changelog", changelog)
+}
+
+fn project_dependents(
+    project_settings: &ProjectSettings,
+    repo_path: &Path,
+) -> Result<Vec<Box<dyn Dependent>>> {
+    project_settings
+        .dependents
+        .iter()
+        .flatten()
+        .map(|dependent| dependents::get_dependent(dependent, repo_path.to_path_buf()))
+        .collect()
+}
+
+/// Stage the dependents and the changelog of a released project
+///
+/// Return the files staged by the dependents.
+fn update_dependents(
+    repo: &Repository,
+    project_settings: &ProjectSettings,
+    dependents: &[Box<dyn Dependent>],
+    version: &Version,
+    previous_release: Option<Oid>,
+    options: &ReleaseOptions,
+    staging: &mut Staging,
+) -> Result<Vec<PathBuf>> {
+    let dependent_options = dependents::DependentUpdateOptions {
+        helm_dependency_update: options.helm_dependency_update,
+    };
+    let mut file_paths = Vec::new();
+    for dependent in dependents {
+        let mut dependent_paths = dependent.update_version(version, &dependent_options, staging)?;
+        file_paths.append(&mut dependent_paths);
+    }
+    changelog::update(repo, project_settings, version, previous_release, staging)?;
+    Ok(file_paths)
+}
+
+/// Return the tag the release creates, if any
+fn release_tag(publication: &Option<Publication>) -> Option<&str> {
+    publication
+        .as_ref()
+        .and_then(|publication| publication.tag.as_ref())
+        .map(|tag| tag.name.as_str())
+}
+
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Commit message and tag of a release, rendered before any file is written
+struct Publication {
+    message: String,
+    /// The tag to create, only with `--tag`
+    tag: Option<ReleaseTag>,
+    remote: String,
+}
+
+/// Name and message of a release tag, no message for a lightweight tag
+struct ReleaseTag {
+    name: String,
+    message: Option<String>,
+}
+
+impl Publication {
+    /// Render the commit message, and the tag if one is created
+    ///
+    /// The tag templates are not rendered without `--tag`, and `{tag}` is
+    /// only available to the commit message when a tag is created.
+    fn render(
+        context: TemplateContext,
+        options: &ReleaseOptions,
+        commit_message: &str,
+        tag_format: &str,
+        tag_message: Option<&str>,
+        remote: &str,
+    ) -> Result<Self> {
+        let (context, tag) = match options.tag {
+            true => {
+                let name = context.render(tag_format)?;
+                let context = context.with("tag", &name);
+                let message = tag_message
+                    .map(|template| context.render(template))
+                    .transpose()?;
+                (context, Some(ReleaseTag { name, message }))
+            }
+            false => (context, None),
+        };
+        Ok(Self {
+            message: context.render(commit_message)?,
+            tag,
+            remote: remote.to_string(),
+        })
+    }
+}
+
+/// Signers and branch of a release, prepared before any file is written
+struct Publisher {
+    options: ReleaseOptions,
+    commit_signer: Option<sign::Signer>,
+    tag_signer: Option<sign::Signer>,
+    branch: Option<String>,
+}
+
+impl Publisher {
+    fn new(repo: &Repository, options: &ReleaseOptions) -> Result<Self> {
+        Ok(Self {
+            options: *options,
+            commit_signer: match options.commit {
+                true => sign::commit_signer(repo, options.sign)?,
+                false => None,
+            },
+            tag_signer: match options.tag {
+                true => sign::tag_signer(repo, options.sign)?,
+                false => None,
+            },
+            branch: match options.push {
+                true => Some(git::current_branch(repo)?),
+                false => None,
+            },
+        })
+    }
+
+    /// Write the staged files, then commit, tag and push as requested
+    ///
+    /// The `pre_release` hooks run before anything is written, and an
+    /// existing tag of the same name fails the release before that. The
+    /// original files are restored if a write, a post update step, the
+    /// commit or the tag fails, and a commit without its tag is undone.
+    /// Files that the post update steps change under the paths of the
+    /// released projects are committed with the staged ones. The `post_release` hooks run last. The written files,
+    /// the commit and the tag are recorded in the report.
+    fn write_and_publish(
+        &self,
+        repo: &Repository,
+        staging: Staging,
+        dependents: &[Box<dyn Dependent>],
+        hooks: &[Hooks],
+        publication: Option<Publication>,
+        report: &mut ReleaseReport,
+    ) -> Result<()> {
+        let repo_path = staging.repo_path().to_path_buf();
+        let dependent_options = dependents::DependentUpdateOptions {
+            helm_dependency_update: self.options.helm_dependency_update,
+        };
+        if let Some(tag) = publication
+            .as_ref()
+            .and_then(|publication| publication.tag.as_ref())
+        {
+            if git::tag_exists(repo, &tag.name) {
+                return Err(anyhow::anyhow!("Tag '{}' already exists", tag.name));
+            }
+        }
+        for hooks in hooks {
+            hooks.pre_release()?;
+        }
+        report.files = staging.paths();
+        let mut backup = staging.apply()?;
+        let mut snapshot = None;
+        let mut committed = None;
+        let written = (|| -> Result<Option<Oid>> {
+            let snapshot = snapshot.insert(hooks::snapshot(
+                repo,
+                &repo_path,
+                &hooks
+                    .iter()
+                    .flat_map(Hooks::paths)
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            )?);
+            let mut updated = Vec::new();
+            for dependent in dependents {
+                updated.extend(dependent.post_update(&dependent_options)?);
+            }
+            for hooks in hooks {
+                hooks.post_update()?;
+            }
+            updated.extend(hooks::changed_since(repo, &repo_path, snapshot)?);
+            for path in updated {
+                if !report.files.contains(&path) {
+                    report.files.push(path);
+                }
+            }
+            let Some(publication) = &publication else {
+                return Ok(None);
+            };
+            let commit_id = *committed.insert(git::commit_files(
+                repo,
+                &report.files,
+                &publication.message,
+                self.commit_signer.as_ref(),
+            )?);
+            if let Some(tag) = &publication.tag {
+                git::tag_commit(
+                    repo,
+                    commit_id,
+                    &tag.name,
+                    tag.message.as_deref(),
+                    self.tag_signer.as_ref(),
+                )
+                .with_context(|| format!("Failed to create tag '{}'", tag.name))?;
+            }
+            Ok(Some(commit_id))
+        })();
+        let commit_id = match written {
+            Ok(commit_id) => commit_id,
+            Err(e) => {
+                if let Some(commit_id) = committed {
+                    if let Err(undo_error) = git::uncommit(repo, commit_id) {
+                        log::error!("Could not undo the release commit: {:#}", undo_error);
+                    }
+                }
+                // Undo the post_update steps as well as the staged files
+                if let Some(snapshot) = &snapshot {
+                    match hooks::originals_since(repo, &repo_path, snapshot) {
+                        Ok(originals) => backup.extend(&repo_path, originals),
+                        Err(restore_error) => log::error!(
+                            "Could not find the files changed after the update: {:#}",
+                            restore_error
+                        ),
+                    }
+                }
+                backup.restore_after(&e);
+                return Err(e);
+            }
+        };
+        if let (Some(commit_id), Some(publication)) = (commit_id, publication) {
+            report.commit_id = Some(commit_id);
+            // Lets a rollback recognize the files of the post update steps
+            if let Err(e) = git::record_release_files(repo, commit_id, &report.files) {
+                log::warn!("Could not record the files of the release commit: {:#}", e);
+            }
+            let mut references = self.branch.iter().cloned().collect::<Vec<_>>();
+            if let Some(tag) = publication.tag {
+                references.push(format!("refs/tags/{}", tag.name));
+                report.tag = Some(tag.name);
+            }
+            if self.options.push {
+                git::push(repo, &publication.remote, &references)?;
+            }
+        }
+        for hooks in hooks {
+            hooks
+                .post_release()
+                .context("The release was completed, but a hook failed afterwards")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{commit_all, init_repo, initial_commit, write};
+    use crate::version::ToVersion;
+
+    #[test]
+    fn test_release() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "1.2.3");
+        write(dir.path(), "deploy/values.yaml", "image: app:1.2.3\n");
+        initial_commit(&repo, &["app/VERSION", "deploy/values.yaml"]);
+        let settings: Settings = serde_yaml::from_str(
+            r#"
+projects:
+  app:
+    type: versionfile
+    path: app
+    manifest_path: app/VERSION
+    scheme: semver
+    dependents:
+      - type: regex
+        path: deploy/values.yaml
+        selector: 'app:\d+\.\d+\.\d+'
+        replace: 'app:{{version}}'
+"#,
+        )
+        .unwrap();
+        let options = ReleaseOptions {
+            commit: true,
+            tag: true,
+            ..Default::default()
+        };
+        let report = release(dir.path(), &settings, "app", &options).unwrap();
+        assert!(report.version.is_none());
+        assert!(report.projects.is_empty());
+
+        write(dir.path(), "app/main.rs", "fn main() {}");
+        commit_all(&repo, "feat: add main");
+        assert_eq!(
+            next_version(dir.path(), &settings, "app").unwrap(),
+            "1.3.0".to_version()
+        );
+        let report = release(dir.path(), &settings, "app", &options).unwrap();
+        assert_eq!(report.version, Some("1.3.0".to_version()));
+        assert_eq!(report.projects[0].previous_version, "1.2.3".to_version());
+        assert_eq!(
+            report.files,
+            vec![
+                PathBuf::from("app/VERSION"),
+                PathBuf::from("deploy/values.yaml")
+            ]
+        );
+        assert_eq!(report.tag.as_deref(), Some("app-1.3.0"));
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(Some(head.id()), report.commit_id);
+        assert_eq!(head.summary(), Some("chore: release app 1.3.0"));
+        assert!(repo.find_reference("refs/tags/app-1.3.0").is_ok());
+    }
+
+    #[test]
+    fn test_release_tag_templates_only_with_tag() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "1.2.3");
+        initial_commit(&repo, &["app/VERSION"]);
+        let config = |commit_message: &str| {
+            format!(
+                r#"
+projects:
+  app:
+    type: versionfile
+    path: app
+    manifest_path: app/VERSION
+    scheme: semver
+    tag_format: "{{unknown}}"
+    commit_message: "{}"
+"#,
+                commit_message
+            )
+        };
+        let options = ReleaseOptions {
+            force: true,
+            commit: true,
+            ..Default::default()
+        };
+
+        let settings: Settings = serde_yaml::from_str(&config("release {tag}")).unwrap();
+        let error = release(dir.path(), &settings, "app", &options).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Unknown placeholder '{tag}' in template 'release {tag}'"));
+
+        let settings: Settings = serde_yaml::from_str(&config("release {version}")).unwrap();
+        let report = release(dir.path(), &settings, "app", &options).unwrap();
+        assert_eq!(report.version, Some("1.2.4".to_version()));
+        assert!(report.tag.is_none());
+        let error = release(
+            dir.path(),
+            &settings,
+            "app",
+            &ReleaseOptions {
+                tag: true,
+                ..options
+            },
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Unknown placeholder '{unknown}'"));
+    }
+
+    #[test]
+    fn test_release_changelog_template() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "1.2.3");
+        write(
+            dir.path(),
+            "app/CHANGELOG.md",
+            "# Changelog\n\n## [1.2.4] - 2024-01-01\n\n- written by hand\n",
+        );
+        initial_commit(&repo, &["app/VERSION", "app/CHANGELOG.md"]);
+        let config = |changelog: &str| {
+            format!(
+                r#"
+projects:
+  app:
+    type: versionfile
+    path: app
+    manifest_path: app/VERSION
+    scheme: semver
+    commit_message: "release {{version}}\n\n{{changelog}}"
+{}
+"#,
+                changelog
+            )
+        };
+        let options = ReleaseOptions {
+            force: true,
+            commit: true,
+            ..Default::default()
+        };
+
+        // The changelog is not managed without the setting, so it is not quoted
+        let settings: Settings = serde_yaml::from_str(&config("")).unwrap();
+        release(dir.path(), &settings, "app", &options).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.message().unwrap().trim_end(), "release 1.2.4");
+
+        write(dir.path(), "app/main.rs", "fn main() {}");
+        commit_all(&repo, "feat: add main");
+        let settings: Settings =
+            serde_yaml::from_str(&config("    changelog: app/CHANGELOG.md")).unwrap();
+        release(dir.path(), &settings, "app", &options).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(
+            head.message().unwrap().trim_end(),
+            "release 1.3.0\n\n### Added\n\n- add main"
+        );
+    }
+
+    #[test]
+    fn test_release_exec() {
+        let (dir, repo) = init_repo();
+        write(
+            dir.path(),
+            "release.sh",
+            r#"request=$(cat)
+case "$request" in
+  *'"operation":"query"'*)
+    version=$(printf '%s' "$request" | sed 's/.*"content":"release \([^"\\]*\)\\n".*/\1/')
+    echo "{\"value\": \"$version\", \"line\": 1}" ;;
+  *)
+    version=$(printf '%s' "$request" | sed 's/.*"value":"\([^"]*\)".*/\1/')
+    printf '{"content": "release %s\\n"}\n' "$version" ;;
+esac
+"#,
+        );
+        write(dir.path(), "app/RELEASE", "release 1.2.3\n");
+        write(dir.path(), "deploy/RELEASE", "release 1.2.3\n");
+        initial_commit(&repo, &["release.sh", "app/RELEASE", "deploy/RELEASE"]);
+        let settings: Settings = serde_yaml::from_str(
+            r#"
+projects:
+  app:
+    type: exec
+    path: app
+    manifest_path: app/RELEASE
+    scheme: semver
+    command: sh release.sh
+    dependents:
+      - type: exec
+        path: deploy/RELEASE
+        command: sh release.sh
+"#,
+        )
+        .unwrap();
+        write(dir.path(), "app/main.rs", "fn main() {}");
+        commit_all(&repo, "feat: add main");
+        let options = ReleaseOptions {
+            commit: true,
+            ..Default::default()
+        };
+        let report = release(dir.path(), &settings, "app", &options).unwrap();
+        assert_eq!(report.version, Some("1.3.0".to_version()));
+        for path in ["app/RELEASE", "deploy/RELEASE"] {
+            assert_eq!(
+                std::fs::read_to_string(dir.path().join(path)).unwrap(),
+                "release 1.3.0\n"
+            );
+        }
+    }
+
+    #[test]
+    fn test_release_glob_dependents() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "1.2.3");
+        write(
+            dir.path(),
+            "deploy/dev/values.yaml",
+            "image:\n  tag: 1.2.3\n",
+        );
+        write(
+            dir.path(),
+            "deploy/prod/values.yaml",
+            "image:\n  tag: 1.2.3\n",
+        );
+        initial_commit(
+            &repo,
+            &[
+                "app/VERSION",
+                "deploy/dev/values.yaml",
+                "deploy/prod/values.yaml",
+            ],
+        );
+        let config = |path: &str| {
+            format!(
+                r#"
+projects:
+  app:
+    type: versionfile
+    path: app
+    manifest_path: app/VERSION
+    scheme: semver
+    dependents:
+      - type: yaml
+        path: {}
+        selector: image.tag
+"#,
+                path
+            )
+        };
+        let options = ReleaseOptions {
+            force: true,
+            commit: true,
+            ..Default::default()
+        };
+
+        let settings: Settings = serde_yaml::from_str(&config("'deploy/**/chart.yaml'")).unwrap();
+        let error = release(dir.path(), &settings, "app", &options).unwrap_err();
+        assert_eq!(
+            error.root_cause().to_string(),
+            "Glob 'deploy/**/chart.yaml' does not match any files"
+        );
+
+        let settings: Settings = serde_yaml::from_str(&config("'deploy/*/values.yaml'")).unwrap();
+        let report = release(dir.path(), &settings, "app", &options).unwrap();
+        assert_eq!(
+            report.files,
+            vec![
+                PathBuf::from("app/VERSION"),
+                PathBuf::from("deploy/dev/values.yaml"),
+                PathBuf::from("deploy/prod/values.yaml")
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("deploy/prod/values.yaml")).unwrap(),
+            "image:\n  tag: 1.2.4\n"
+        );
+    }
+
+    #[test]
+    fn test_release_failure_restores_hook_files() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "1.2.3");
+        write(dir.path(), "app/openapi.json", "{}");
+        initial_commit(&repo, &["app/VERSION", "app/openapi.json"]);
+        let settings: Settings = serde_yaml::from_str(
+            r#"
+projects:
+  app:
+    type: versionfile
+    path: app
+    manifest_path: app/VERSION
+    hooks:
+      post_update:
+        - echo changed > openapi.json
+        - echo new > generated.txt
+"#,
+        )
+        .unwrap();
+        let options = ReleaseOptions {
+            force: true,
+            commit: true,
+            ..Default::default()
+        };
+        std::fs::write(repo.path().join("index.lock"), "").unwrap();
+        assert!(release(dir.path(), &settings, "app", &options).is_err());
+        for (path, content) in [("app/VERSION", "1.2.3"), ("app/openapi.json", "{}")] {
+            assert_eq!(
+                std::fs::read_to_string(dir.path().join(path)).unwrap(),
+                content
+            );
+        }
+        assert!(!dir.path().join("app/generated.txt").exists());
+    }
+
+    #[test]
+    fn test_release_failure_restores_files() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "25.1.0");
+        write(dir.path(), "README.md", "app 25.1.0\n");
+        write(dir.path(), "deploy/values.yaml", "image:\n  tag: 25.1.0\n");
+        initial_commit(&repo, &["app/VERSION", "README.md", "deploy/values.yaml"]);
+        let config = |selector: &str| {
+            format!(
+                r#"
+projects:
+  app:
+    type: versionfile
+    path: app
+    manifest_path: app/VERSION
+    dependents:
+      - type: regex
+        path: README.md
+        selector: 'app \d+\.\d+\.\d+'
+        replace: 'app {{{{version}}}}'
+      - type: yaml
+        path: deploy/values.yaml
+        selector: {}
+"#,
+                selector
+            )
+        };
+        let unchanged = || {
+            for (path, content) in [
+                ("app/VERSION", "25.1.0"),
+                ("README.md", "app 25.1.0\n"),
+                ("deploy/values.yaml", "image:\n  tag: 25.1.0\n"),
+            ] {
+                assert_eq!(
+                    std::fs::read_to_string(dir.path().join(path)).unwrap(),
+                    content
+                );
+            }
+        };
+        let options = ReleaseOptions {
+            force: true,
+            commit: true,
+            ..Default::default()
+        };
+
+        let settings: Settings = serde_yaml::from_str(&config("image.version")).unwrap();
+        assert!(release(dir.path(), &settings, "app", &options).is_err());
+        unchanged();
+
+        let settings: Settings = serde_yaml::from_str(&config("image.tag")).unwrap();
+        std::fs::write(repo.path().join("index.lock"), "").unwrap();
+        assert!(release(dir.path(), &settings, "app", &options).is_err());
+        unchanged();
+
+        std::fs::remove_file(repo.path().join("index.lock")).unwrap();
+        let report = release(dir.path(), &settings, "app", &options).unwrap();
+        assert_eq!(report.files.len(), 3);
+        assert!(report.commit_id.is_some());
+    }
+
+    #[test]
+    fn test_set_version_group_member() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "api/VERSION", "1.2.0");
+        write(dir.path(), "worker/VERSION", "1.2.0");
+        initial_commit(&repo, &["api/VERSION", "worker/VERSION"]);
+        let settings: Settings = serde_yaml::from_str(
+            r#"
+projects:
+  api:
+    type: versionfile
+    path: api
+    manifest_path: api/VERSION
+  worker:
+    type: versionfile
+    path: worker
+    manifest_path: worker/VERSION
+groups:
+  backend:
+    members: [api, worker]
+"#,
+        )
+        .unwrap();
+        let error = set_version(
+            dir.path(),
+            &settings,
+            "api",
+            &"9.9.9".to_version(),
+            &ReleaseOptions::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Project 'api' is a member of group 'backend' and shares its version, release the group instead"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("api/VERSION")).unwrap(),
+            "1.2.0"
+        );
+    }
+
+    #[test]
+    fn test_release_tag_failure() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "1.2.3");
+        let initial_id = initial_commit(&repo, &["app/VERSION"]);
+        let settings: Settings = serde_yaml::from_str(
+            "projects:\n  app:\n    type: versionfile\n    path: app\n    manifest_path: app/VERSION\n    scheme: semver\n",
+        )
+        .unwrap();
+        let options = ReleaseOptions {
+            force: true,
+            commit: true,
+            tag: true,
+            ..Default::default()
+        };
+        let unchanged = || {
+            assert_eq!(repo.head().unwrap().target(), Some(initial_id));
+            assert_eq!(
+                std::fs::read_to_string(dir.path().join("app/VERSION")).unwrap(),
+                "1.2.3"
+            );
+            assert!(git::dirty_files(&repo, &["."]).unwrap().is_empty());
+        };
+
+        repo.tag_lightweight(
+            "app-1.2.4",
+            &repo.find_object(initial_id, None).unwrap(),
+            false,
+        )
+        .unwrap();
+        let error = release(dir.path(), &settings, "app", &options).unwrap_err();
+        assert_eq!(error.to_string(), "Tag 'app-1.2.4' already exists");
+        unchanged();
+
+        // The tag cannot be written, so the release commit is undone
+        repo.tag_delete("app-1.2.4").unwrap();
+        std::fs::write(repo.path().join("refs/tags/app-1.2.4.lock"), "").unwrap();
+        let error = release(dir.path(), &settings, "app", &options).unwrap_err();
+        assert_eq!(error.to_string(), "Failed to create tag 'app-1.2.4'");
+        unchanged();
+    }
+}
@@ -0,0 +1,548 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use git2::{Commit, Oid, Repository};
+use globset::GlobMatcher;
+
+use crate::{
+    dependents::glob,
+    git,
+    projects::{self, ProjectFile},
+    settings::{ProjectSettings, Settings},
+    sign,
+    template::TemplateContext,
+    version::Version,
+};
+
+/// How the release commit is undone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollbackMode {
+    /// Move the branch back to the parent of the release commit
+    Reset,
+    /// Create a commit that reverts the release commit
+    Revert,
+}
+
+/// Result of a rollback
+#[derive(Debug)]
+pub struct RollbackReport {
+    /// The version of the release that was rolled back
+    pub version: Version,
+    /// The release commit
+    pub release_id: Oid,
+    /// HEAD after the rollback
+    pub head_id: Oid,
+    pub deleted_tags: Vec<String>,
+}
+
+/// Undo the last release of a project
+///
+/// HEAD must be a release commit of the project: it changes the version
+/// line of the manifest, only touches the files the release wrote, and its
+/// message matches the commit message template. Fails if the commit is
+/// already on the upstream branch, or if the project is a group member.
+pub fn rollback(
+    repo: &Repository,
+    project: &str,
+    settings: &Settings,
+    project_file: &dyn ProjectFile,
+    mode: RollbackMode,
+) -> Result<RollbackReport> {
+    if let Some(group) = settings.group_of(project) {
+        return Err(anyhow::anyhow!(
+            "Project '{}' is a member of group '{}', roll back the group instead",
+            project,
+            group
+        ));
+    }
+    let project_settings = &project_file.base().settings;
+    let head = repo.head()?.peel_to_commit()?;
+    let (version, previous_version) = released_versions(repo, project, project_file, &head)?;
+    let mut files = ReleaseFiles::default();
+    files.add_project(project_settings, project_file)?;
+    let context = TemplateContext::new()
+        .with("project", project)
+        .with("prefix", project_settings.tag_prefix_for(project))
+        .with("version", &version)
+        .with("previous_version", &previous_version);
+    undo(
+        repo,
+        &head,
+        ReleaseCommit {
+            name: project,
+            version,
+            context,
+            files,
+            commit_message: settings.commit_message_template(project_settings),
+            tag_format: project_settings.tag_format_template(),
+            remote: settings.remote(project_settings),
+        },
+        mode,
+    )
+}
+
+/// Undo the last release of a group
+///
+/// HEAD must change the version of every member, and otherwise meet the
+/// same conditions as for a project, with the templates of the group.
+pub fn rollback_group(
+    repo: &Repository,
+    name: &str,
+    settings: &Settings,
+    repo_path: &Path,
+    mode: RollbackMode,
+) -> Result<RollbackReport> {
+    let group = settings.group_settings(name)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let mut versions = Vec::new();
+    let mut files = ReleaseFiles::default();
+    for member in &group.members {
+        let project_settings = settings.project_settings(member)?;
+        let project_file =
+            projects::get_project_file(project_settings.clone(), repo_path.to_path_buf());
+        versions.push(released_versions(repo, name, project_file.as_ref(), &head)?);
+        files.add_project(project_settings, project_file.as_ref())?;
+    }
+    let (version, _) = versions
+        .iter()
+        .max()
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("Group '{}' has no members", name))?;
+    let previous_version = versions
+        .iter()
+        .map(|(_, previous_version)| previous_version)
+        .max()
+        .cloned()
+        .unwrap_or_else(|| version.clone());
+    let context = TemplateContext::new()
+        .with("project", name)
+        .with("prefix", group.tag_prefix_for(name))
+        .with("version", &version)
+        .with("previous_version", &previous_version)
+        .with("members", group.members.join(", "));
+    undo(
+        repo,
+        &head,
+        ReleaseCommit {
+            name,
+            version,
+            context,
+            files,
+            commit_message: group.commit_message_template(settings),
+            tag_format: group.tag_format_template(),
+            remote: group.remote(settings),
+        },
+        mode,
+    )
+}
+
+/// Return the version a release commit set and the version before it
+///
+/// Fails if the commit does not change the version line of the manifest.
+fn released_versions(
+    repo: &Repository,
+    name: &str,
+    project_file: &dyn ProjectFile,
+    head: &Commit,
+) -> Result<(Version, Version)> {
+    let manifest_path = project_file.get_manifest_file_path()?;
+    let parent_id = head
+        .parent_id(0)
+        .map_err(|_| anyhow::anyhow!("HEAD has no parent commit, it cannot be a release commit"))?;
+    let version_context =
+        project_file.version_context(&git::read_file_at(repo, &manifest_path, head.id())?)?;
+    let version_commit =
+        git::get_commit_id_for_line(repo, &manifest_path, version_context.line_number)?;
+    if version_commit != head.id() {
+        return Err(anyhow::anyhow!(
+            "HEAD is not a release commit of '{}', it does not change the version in '{}'",
+            name,
+            manifest_path.display()
+        ));
+    }
+    let previous_version = project_file
+        .version_context(&git::read_file_at(repo, &manifest_path, parent_id)?)?
+        .version;
+    Ok((version_context.version, previous_version))
+}
+
+/// The files a release commit may change
+#[derive(Default)]
+struct ReleaseFiles {
+    /// Files and directories, a file under a directory is allowed too
+    paths: Vec<PathBuf>,
+    globs: Vec<GlobMatcher>,
+}
+
+impl ReleaseFiles {
+    /// Allow the manifest, the changelog and the dependent paths of a project
+    fn add_project(
+        &mut self,
+        project_settings: &ProjectSettings,
+        project_file: &dyn ProjectFile,
+    ) -> Result<()> {
+        self.paths.push(project_file.get_manifest_file_path()?);
+        self.paths
+            .extend(project_settings.changelog.iter().cloned());
+        for dependent in project_settings.dependents.iter().flatten() {
+            match glob::is_glob(&dependent.dependent_path) {
+                true => self.globs.push(glob::matcher(&dependent.dependent_path)?),
+                false => self.paths.push(dependent.dependent_path.clone()),
+            }
+        }
+        Ok(())
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.paths.iter().any(|allowed| path.starts_with(allowed))
+            || path
+                .ancestors()
+                .any(|path| self.globs.iter().any(|glob| glob.is_match(path)))
+    }
+}
+
+/// What a release commit of a project or a group looks like
+struct ReleaseCommit<'a> {
+    /// The project or group name
+    name: &'a str,
+    version: Version,
+    /// Context for matching the commit message and the tags
+    context: TemplateContext,
+    files: ReleaseFiles,
+    commit_message: &'a str,
+    tag_format: &'a str,
+    remote: &'a str,
+}
+
+/// Check that HEAD is the release commit, then undo it and delete its tags
+///
+/// The files that the release recorded for the commit, such as the output
+/// of post update hooks, are allowed along with the configured ones.
+fn undo(
+    repo: &Repository,
+    head: &Commit,
+    release: ReleaseCommit,
+    mode: RollbackMode,
+) -> Result<RollbackReport> {
+    let mut files = release.files;
+    files
+        .paths
+        .extend(git::release_files(repo, head.id())?.into_iter().flatten());
+    let unexpected_files = git::changed_files(repo, head.id())?
+        .into_iter()
+        .filter(|path| !files.contains(path))
+        .collect::<Vec<_>>();
+    if !unexpected_files.is_empty() {
+        return Err(anyhow::anyhow!(
+            "HEAD is not a release commit of '{}', it changes files outside the release: {}",
+            release.name,
+            unexpected_files
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    let mut context = release.context;
+    if let Ok(tag) = context.render(release.tag_format) {
+        context = context.with("tag", tag);
+    }
+    let message_pattern = context.to_regex(release.commit_message)?;
+    if !message_pattern.is_match(head.message().unwrap_or_default().trim_end()) {
+        return Err(anyhow::anyhow!(
+            "HEAD is not a release commit of '{}', the commit message does not match '{}'",
+            release.name,
+            release.commit_message
+        ));
+    }
+
+    if let Some(upstream) = git::pushed_to(repo, head.id(), release.remote)? {
+        return Err(anyhow::anyhow!(
+            "The release commit has already been pushed to '{}'",
+            upstream
+        ));
+    }
+
+    let tag_pattern = context.to_regex(release.tag_format)?;
+    let tags = git::tags_pointing_at(repo, head.id())?
+        .into_iter()
+        .filter(|tag| tag_pattern.is_match(tag))
+        .collect::<Vec<_>>();
+
+    let head_id = match mode {
+        RollbackMode::Reset => {
+            let modified = git::dirty_files(repo, &["."])?
+                .into_iter()
+                .filter(|(_, status)| !status.is_wt_new())
+                .collect::<Vec<_>>();
+            if !modified.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "The repository has uncommitted changes that a reset would discard: {}",
+                    modified
+                        .iter()
+                        .map(|(path, _)| path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            git::reset_head(repo)?
+        }
+        RollbackMode::Revert => git::revert_head(repo, sign::commit_signer(repo, false)?.as_ref())?,
+    };
+    for tag in &tags {
+        log::info!("Deleting tag {}", tag);
+        repo.tag_delete(tag)?;
+    }
+    Ok(RollbackReport {
+        version: release.version,
+        release_id: head.id(),
+        head_id,
+        deleted_tags: tags,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        projects::get_project_file,
+        testing::{commit_all, init_repo, initial_commit, write},
+        version::ToVersion,
+    };
+
+    const CONFIG: &str = r#"
+projects:
+  app:
+    type: versionfile
+    path: app
+    manifest_path: app/VERSION
+    dependents:
+      - type: regex
+        path: deploy/values.yaml
+        selector: "app:.*"
+"#;
+
+    fn setup() -> (tempfile::TempDir, Repository, Settings, Oid) {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "25.1.0");
+        write(dir.path(), "deploy/values.yaml", "app:25.1.0");
+        initial_commit(&repo, &["app/VERSION", "deploy/values.yaml"]);
+        write(dir.path(), "app/VERSION", "25.1.1");
+        write(dir.path(), "deploy/values.yaml", "app:25.1.1");
+        let release_id = commit_all(&repo, "chore: release app 25.1.1");
+        {
+            let release = repo.find_object(release_id, None).unwrap();
+            repo.tag_lightweight("app-25.1.1", &release, false).unwrap();
+            repo.tag_lightweight("unrelated", &release, false).unwrap();
+        }
+        let settings: Settings = serde_yaml::from_str(CONFIG).unwrap();
+        (dir, repo, settings, release_id)
+    }
+
+    #[test]
+    fn test_rollback_reset() {
+        let (dir, repo, settings, release_id) = setup();
+        let project_file = get_project_file(
+            settings.project_settings("app").unwrap().clone(),
+            dir.path().to_path_buf(),
+        );
+        let report = rollback(
+            &repo,
+            "app",
+            &settings,
+            project_file.as_ref(),
+            RollbackMode::Reset,
+        )
+        .unwrap();
+        assert_eq!(report.release_id, release_id);
+        assert_eq!(report.deleted_tags, vec!["app-25.1.1"]);
+        assert_eq!(repo.head().unwrap().target(), Some(report.head_id));
+        assert!(repo.find_reference("refs/tags/app-25.1.1").is_err());
+        assert!(repo.find_reference("refs/tags/unrelated").is_ok());
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("app/VERSION")).unwrap(),
+            "25.1.0"
+        );
+    }
+
+    #[test]
+    fn test_rollback_revert() {
+        let (dir, repo, settings, release_id) = setup();
+        let project_file = get_project_file(
+            settings.project_settings("app").unwrap().clone(),
+            dir.path().to_path_buf(),
+        );
+        let report = rollback(
+            &repo,
+            "app",
+            &settings,
+            project_file.as_ref(),
+            RollbackMode::Revert,
+        )
+        .unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id(), report.head_id);
+        assert_eq!(head.parent_id(0).unwrap(), release_id);
+        assert_eq!(head.summary(), Some("Revert \"chore: release app 25.1.1\""));
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("deploy/values.yaml")).unwrap(),
+            "app:25.1.0"
+        );
+    }
+
+    #[test]
+    fn test_rollback_not_a_release() {
+        let (dir, repo, settings, _) = setup();
+        write(dir.path(), "app/main.rs", "fn main() {}");
+        commit_all(&repo, "feat: add main");
+        let project_file = get_project_file(
+            settings.project_settings("app").unwrap().clone(),
+            dir.path().to_path_buf(),
+        );
+        let result = rollback(
+            &repo,
+            "app",
+            &settings,
+            project_file.as_ref(),
+            RollbackMode::Reset,
+        );
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("does not change the version"));
+    }
+
+    #[test]
+    fn test_rollback_pushed() {
+        let (dir, repo, settings, release_id) = setup();
+        let branch = git::current_branch(&repo).unwrap();
+        let name = branch.strip_prefix("refs/heads/").unwrap();
+        repo.reference(
+            &format!("refs/remotes/origin/{}", name),
+            release_id,
+            false,
+            "fetch",
+        )
+        .unwrap();
+        let project_file = get_project_file(
+            settings.project_settings("app").unwrap().clone(),
+            dir.path().to_path_buf(),
+        );
+        let result = rollback(
+            &repo,
+            "app",
+            &settings,
+            project_file.as_ref(),
+            RollbackMode::Reset,
+        );
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("already been pushed"));
+        assert!(repo.find_reference("refs/tags/app-25.1.1").is_ok());
+    }
+
+    #[test]
+    fn test_rollback_post_update_hook() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "app/VERSION", "25.1.0");
+        initial_commit(&repo, &["app/VERSION"]);
+        let settings: Settings = serde_yaml::from_str(
+            r#"
+projects:
+  app:
+    type: versionfile
+    path: app
+    manifest_path: app/VERSION
+    hooks:
+      post_update: ["echo $MONOVERSE_VERSION > generated.txt"]
+"#,
+        )
+        .unwrap();
+        let options = crate::projects::ReleaseOptions {
+            force: true,
+            commit: true,
+            tag: true,
+            ..Default::default()
+        };
+        let release = crate::release::release(dir.path(), &settings, "app", &options).unwrap();
+        assert!(release.files.contains(&PathBuf::from("app/generated.txt")));
+        let project_file = get_project_file(
+            settings.project_settings("app").unwrap().clone(),
+            dir.path().to_path_buf(),
+        );
+        let report = rollback(
+            &repo,
+            "app",
+            &settings,
+            project_file.as_ref(),
+            RollbackMode::Reset,
+        )
+        .unwrap();
+        assert_eq!(Some(report.release_id), release.commit_id);
+        assert_eq!(report.deleted_tags, vec![release.tag.unwrap()]);
+        assert!(!dir.path().join("app/generated.txt").exists());
+    }
+
+    #[test]
+    fn test_rollback_group() {
+        let (dir, repo) = init_repo();
+        write(dir.path(), "api/VERSION", "1.2.0");
+        write(dir.path(), "worker/VERSION", "1.1.0");
+        initial_commit(&repo, &["api/VERSION", "worker/VERSION"]);
+        let settings: Settings = serde_yaml::from_str(
+            r#"
+projects:
+  api:
+    type: versionfile
+    path: api
+    manifest_path: api/VERSION
+    scheme: semver
+  worker:
+    type: versionfile
+    path: worker
+    manifest_path: worker/VERSION
+    scheme: semver
+groups:
+  backend:
+    members: [api, worker]
+"#,
+        )
+        .unwrap();
+        let options = crate::projects::ReleaseOptions {
+            force: true,
+            commit: true,
+            tag: true,
+            ..Default::default()
+        };
+        let release = crate::release::release(dir.path(), &settings, "backend", &options).unwrap();
+        assert_eq!(release.tag.as_deref(), Some("backend-1.2.1"));
+
+        let project_file = get_project_file(
+            settings.project_settings("api").unwrap().clone(),
+            dir.path().to_path_buf(),
+        );
+        let error = rollback(
+            &repo,
+            "api",
+            &settings,
+            project_file.as_ref(),
+            RollbackMode::Reset,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Project 'api' is a member of group 'backend', roll back the group instead"
+        );
+
+        let report =
+            rollback_group(&repo, "backend", &settings, dir.path(), RollbackMode::Reset).unwrap();
+        assert_eq!(report.version, "1.2.1".to_version());
+        assert_eq!(report.deleted_tags, vec!["backend-1.2.1"]);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("worker/VERSION")).unwrap(),
+            "1.1.0"
+        );
+    }
+}
@@ -1,48 +1,421 @@
-use anyhow::Result;
-use config::Config;
-use serde::Deserialize;
+use anyhow::{Context, Result};
+use config::{Config, Source};
+use regex::Regex;
+use schemars::schema::{RootSchema, Schema};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use serde_path_to_error::Segment;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
 };
 
-use crate::{dependents::DependentType, projects::ProjectType};
+use crate::{
+    dependents::{glob, DependentType},
+    ignore::IgnoreRules,
+    projects::ProjectType,
+};
 
-#[derive(Deserialize, Debug)]
+/// Monoverse configuration
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct Settings {
+    /// Map of project names to project settings
     pub projects: HashMap<String, ProjectSettings>,
+    /// Template for the release commit message
+    pub commit_message: Option<String>,
+    /// Remote that releases are pushed to
+    pub remote: Option<String>,
+    /// Map of group names to groups of projects that are released together
+    #[serde(default)]
+    pub groups: HashMap<String, GroupSettings>,
+    /// Globs that narrow the search for project files, such as
+    /// `services/*/monoverse.project.yaml`
+    ///
+    /// By default every project file in the repository is read.
+    #[serde(default)]
+    pub project_files: Vec<PathBuf>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// Commit message used when neither the project nor the global settings define one
+pub const DEFAULT_COMMIT_MESSAGE: &str = "chore: release {project} {version}";
+
+/// Tag name used when the project does not define a tag format
+pub const DEFAULT_TAG_FORMAT: &str = "{prefix}{version}";
+
+/// Annotated tag message used when the project does not define one
+pub const DEFAULT_TAG_MESSAGE: &str = "{tag}";
+
+/// Remote used when neither the project nor the global settings define one
+pub const DEFAULT_REMOTE: &str = "origin";
+
+/// Name of the project files that are discovered in project directories
+pub const PROJECT_FILE_NAME: &str = "monoverse.project.yaml";
+
+/// File extensions the configuration file can have
+pub const CONFIG_EXTENSIONS: [&str; 7] = ["yaml", "yml", "toml", "json", "json5", "ini", "ron"];
+
+/// Settings of a single project
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ProjectSettings {
+    /// The type of the project
     #[serde(rename = "type")]
     pub project_type: ProjectType,
+    /// The path to the project, defaults to the repository root
     #[serde(default, rename = "path")]
     pub project_path: PathBuf,
+    /// The path to the manifest file, overrides the default for the project type
     pub manifest_path: Option<PathBuf>,
+    /// The versioning scheme of the project
+    #[serde(default)]
+    pub scheme: VersionScheme,
+    /// Prefix for tag creation, defaults to `<project-name>-`
     pub tag_prefix: Option<String>,
+    /// Template for the tag name
+    pub tag_format: Option<String>,
+    /// Template for the annotated tag message
+    pub tag_message: Option<String>,
+    /// Type of the created tag
+    #[serde(default)]
+    pub tag_type: TagType,
+    /// Template for the release commit message, overrides the global one
+    pub commit_message: Option<String>,
+    /// Remote that releases are pushed to, overrides the global one
+    pub remote: Option<String>,
+    /// Changelog file to generate on release
+    pub changelog: Option<PathBuf>,
+    /// Template file for `monoverse notes`
+    pub notes_template: Option<PathBuf>,
+    /// Selector for the version field, required for `toml` and `yaml` projects
     pub selector: Option<String>,
+    /// Executable that queries and edits the manifest, required for `exec` projects
+    pub command: Option<String>,
+    /// Dependent files to update on release
     pub dependents: Option<Vec<DependentSettings>>,
+    /// Scope of the uncommitted changes check
+    #[serde(default)]
+    pub dirty_check: DirtyCheck,
+    /// Rules for commits that do not trigger a release
+    pub ignore: Option<Vec<IgnoreRule>>,
+    /// Commands to run around a release
+    #[serde(default)]
+    pub hooks: HookSettings,
+}
+
+/// Shell commands run around a release of a project
+///
+/// Commands run in the project directory with the release described in
+/// `MONOVERSE_*` environment variables.
+#[derive(Deserialize, JsonSchema, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct HookSettings {
+    /// Commands run before any file is written
+    #[serde(default)]
+    pub pre_release: Vec<String>,
+    /// Commands run after the files are written and before the commit,
+    /// the files they change are added to the commit
+    #[serde(default)]
+    pub post_update: Vec<String>,
+    /// Commands run after the release is committed, tagged and pushed
+    #[serde(default)]
+    pub post_release: Vec<String>,
+}
+
+/// Rule for commits that do not count as changes to a project
+///
+/// A commit matches the rule if it matches every defined condition.
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct IgnoreRule {
+    /// Regular expression matched against the commit message
+    pub message: Option<String>,
+    /// Regular expression matched against the author as `Name <email>`
+    pub author: Option<String>,
+    /// Key of a trailer the commit message must have
+    pub trailer: Option<String>,
+}
+
+/// Scope of the uncommitted changes check that runs before a release
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DirtyCheck {
+    /// Check the project path, the manifest file and every dependent path
+    #[default]
+    Project,
+    /// Check only the manifest file
+    Manifest,
+    /// Skip the check
+    None,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// Versioning scheme of a project
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionScheme {
+    /// Calendar versioning in the `YY.MM.MICRO` format
+    #[default]
+    Calver,
+    /// Semantic versioning with the bump level derived from Conventional Commits
+    Semver,
+}
+
+/// Kind of git tag created on release
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagType {
+    /// Tag object with a message, can be signed
+    #[default]
+    Annotated,
+    /// Plain reference to the release commit
+    Lightweight,
+}
+
+/// Projects that are released together with a shared version
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GroupSettings {
+    /// Names of the projects in the group
+    pub members: Vec<String>,
+    /// Prefix of the group release tag, defaults to `<group-name>-`
+    pub tag_prefix: Option<String>,
+    /// Template for the group release tag name
+    pub tag_format: Option<String>,
+    /// Template for the annotated tag message
+    pub tag_message: Option<String>,
+    /// Kind of tag to create
+    #[serde(default)]
+    pub tag_type: TagType,
+    /// Template for the release commit message, overrides the global one
+    pub commit_message: Option<String>,
+    /// Remote that releases are pushed to, overrides the global one
+    pub remote: Option<String>,
+}
+
+/// A file that references the project version
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct DependentSettings {
+    /// The type of the dependent
     #[serde(rename = "type")]
     pub dependent_type: DependentType,
+    /// The path to the dependent file, or the chart directory for `helm`
+    ///
+    /// A glob such as `deploy/**/values*.yaml` updates every matched file.
     #[serde(default, rename = "path")]
     pub dependent_path: PathBuf,
+    /// Selector for the version in the dependent file
     pub selector: Option<String>,
+    /// String to replace the selector match with, only for `regex`
+    ///
+    /// `{{version}}` is the next version and `{{name}}` is the named
+    /// capture group `name` of the selector.
     pub replace: Option<String>,
+    /// Replace every match instead of the first one, only for `regex`
+    #[serde(default)]
+    pub replace_all: bool,
+    /// Number of matches the selector must have, only for `regex`
+    pub expect_matches: Option<usize>,
+    /// Executable that queries and edits the file, required for `exec`
+    pub command: Option<String>,
 }
 
 impl Settings {
-    pub fn new<P: AsRef<Path>>(repo_path: P) -> Result<Self> {
-        let config_path = repo_path.as_ref().join("monoverse");
+    /// Return the JSON Schema of the configuration file
+    ///
+    /// `defaults`, `templates` and `extends` are merged away before the
+    /// settings are deserialized, so they are added to the schema here.
+    /// Projects do not require a `type` as it may come from a template.
+    pub fn json_schema() -> RootSchema {
+        let mut root = schemars::schema_for!(Settings);
+        let Some(Schema::Object(project)) = root.definitions.get_mut("ProjectSettings") else {
+            unreachable!("ProjectSettings is a struct");
+        };
+        project.object().required.clear();
+        let mut template = project.clone();
+        template.metadata().description =
+            Some("Partial project settings that projects can extend".to_string());
+        project.object().properties.insert(
+            "extends".to_string(),
+            schema_from_json(serde_json::json!({
+                "description": "Names of the templates to extend, applied in order",
+                "anyOf": [
+                    { "type": "string" },
+                    { "type": "array", "items": { "type": "string" } }
+                ]
+            })),
+        );
+        root.definitions
+            .insert("ProjectTemplate".to_string(), Schema::Object(template));
+        let properties = &mut root.schema.object().properties;
+        properties.insert(
+            "defaults".to_string(),
+            schema_from_json(serde_json::json!({
+                "description": "Settings that every project starts from",
+                "allOf": [{ "$ref": "#/definitions/ProjectTemplate" }]
+            })),
+        );
+        properties.insert(
+            "templates".to_string(),
+            schema_from_json(serde_json::json!({
+                "description": "Named project settings that projects can extend",
+                "type": "object",
+                "additionalProperties": { "$ref": "#/definitions/ProjectTemplate" }
+            })),
+        );
+        root
+    }
+
+    /// Read the configuration of a repository
+    ///
+    /// Projects are read from the configuration file and from
+    /// `monoverse.project.yaml` files in project directories. The
+    /// configuration file defaults to `monoverse.{yaml,json,toml}` at the
+    /// repository root. `MONOVERSE_*` environment variables override
+    /// settings from the files.
+    pub fn new<P: AsRef<Path>>(repo_path: P, config_file: Option<&Path>) -> Result<Self> {
+        Self::from_sources(repo_path.as_ref(), config_file, environment())
+    }
+
+    fn from_sources(
+        repo_path: &Path,
+        config_file: Option<&Path>,
+        environment: config::Environment,
+    ) -> Result<Self> {
+        let config_path = repo_path.join("monoverse");
+        let (file, source) = match config_file {
+            Some(config_file) => (
+                config_file.to_path_buf(),
+                config::File::from(config_file).required(true),
+            ),
+            None => (
+                Self::config_file(repo_path).unwrap_or(config_path.clone()),
+                config::File::with_name(config_path.to_str().unwrap()).required(false),
+            ),
+        };
+        let overrides = environment
+            .collect()
+            .context("Could not read the environment")?
+            .into_keys()
+            .collect::<Vec<_>>();
         let settings = Config::builder()
-            .add_source(config::File::with_name(config_path.to_str().unwrap()))
-            .build()?;
-        let deserialized: Self = settings.try_deserialize()?;
-        Ok(deserialized)
+            .add_source(source)
+            .add_source(environment)
+            .build()
+            .with_context(|| format!("Could not read {}", file.display()))?;
+        let mut value: Value = settings.try_deserialize()?;
+        let globs = match value.get("project_files") {
+            Some(globs) => serde_json::from_value(globs.clone()).map_err(|_| {
+                anyhow::anyhow!(
+                    "{}: 'project_files' must be a list of globs",
+                    file.display()
+                )
+            })?,
+            None => Vec::new(),
+        };
+        let project_files = discover_project_files(repo_path, &globs)
+            .map_err(|e| anyhow::anyhow!("{}: {:#}", file.display(), e))?;
+        if config_file.is_none() && !file.exists() && project_files.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No configuration found in {}, create a monoverse.yaml file or a {} file in a project directory",
+                repo_path.display(),
+                PROJECT_FILE_NAME
+            ));
+        }
+        let project_files = add_project_files(&mut value, &file, repo_path, &project_files)?;
+        Self::from_value(value, &file, &project_files, &overrides)
+    }
+
+    /// Deserialize settings after merging defaults and templates into the projects
+    ///
+    /// `project_files` maps the projects defined in project files to the
+    /// file and `overrides` lists the keys set by environment variables, so
+    /// that errors point to the right place.
+    fn from_value(
+        mut value: Value,
+        file: &Path,
+        project_files: &HashMap<String, PathBuf>,
+        overrides: &[String],
+    ) -> Result<Self> {
+        check_unused_templates(&value).map_err(|e| anyhow::anyhow!("{}: {}", file.display(), e))?;
+        let origins = apply_templates(&mut value)
+            .map_err(|e| anyhow::anyhow!("{}: {}", file.display(), e))?;
+        let settings: Self = serde_path_to_error::deserialize(value).map_err(|e| {
+            describe_error(
+                file,
+                e.path(),
+                &e.inner().to_string(),
+                &origins,
+                project_files,
+                overrides,
+            )
+        })?;
+        settings
+            .check_groups()
+            .map_err(|e| anyhow::anyhow!("{}: {}", file.display(), e))?;
+        let mut names = settings.projects.keys().collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            let file = project_files.get(name).map_or(file, PathBuf::as_path);
+            settings.projects[name].check().map_err(|e| {
+                anyhow::anyhow!("{}: {:#} in project '{}'", file.display(), e, name)
+            })?;
+        }
+        Ok(settings)
+    }
+
+    /// Check that groups only contain known projects, each in one group
+    fn check_groups(&self) -> Result<()> {
+        let mut names = self.groups.keys().collect::<Vec<_>>();
+        names.sort();
+        let mut grouped = HashMap::new();
+        for name in names {
+            let group = &self.groups[name];
+            if self.projects.contains_key(name) {
+                return Err(anyhow::anyhow!(
+                    "Group '{}' has the same name as a project",
+                    name
+                ));
+            }
+            if group.members.is_empty() {
+                return Err(anyhow::anyhow!("Group '{}' has no members", name));
+            }
+            let mut schemes = Vec::new();
+            for member in &group.members {
+                let Some(project_settings) = self.projects.get(member) else {
+                    return Err(anyhow::anyhow!(
+                        "Group '{}' has an unknown member '{}'",
+                        name,
+                        member
+                    ));
+                };
+                if let Some(other) = grouped.insert(member, name) {
+                    return Err(anyhow::anyhow!(
+                        "Project '{}' is a member of both group '{}' and group '{}'",
+                        member,
+                        other,
+                        name
+                    ));
+                }
+                schemes.push(project_settings.scheme);
+            }
+            if schemes.iter().any(|scheme| scheme != &schemes[0]) {
+                return Err(anyhow::anyhow!(
+                    "Members of group '{}' use different version schemes",
+                    name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the configuration file in the repository, if there is one
+    pub fn config_file(repo_path: &Path) -> Option<PathBuf> {
+        CONFIG_EXTENSIONS
+            .iter()
+            .map(|extension| repo_path.join(format!("monoverse.{}", extension)))
+            .find(|path| path.exists())
     }
 
     pub fn project_settings(&self, project_name: &str) -> Result<&ProjectSettings> {
@@ -50,6 +423,79 @@ impl Settings {
             .get(project_name)
             .ok_or_else(|| anyhow::anyhow!("No project found with name: {}", project_name))
     }
+
+    pub fn group_settings(&self, group_name: &str) -> Result<&GroupSettings> {
+        self.groups
+            .get(group_name)
+            .ok_or_else(|| anyhow::anyhow!("No group found with name: {}", group_name))
+    }
+
+    /// Return the group a project is a member of, if any
+    pub fn group_of(&self, project_name: &str) -> Option<&str> {
+        self.groups
+            .iter()
+            .find(|(_, group)| group.members.iter().any(|member| member == project_name))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Return the release commit message template for a project
+    ///
+    /// The project level template takes precedence over the global one.
+    pub fn commit_message_template<'a>(&'a self, project_settings: &'a ProjectSettings) -> &'a str {
+        project_settings
+            .commit_message
+            .as_deref()
+            .or(self.commit_message.as_deref())
+            .unwrap_or(DEFAULT_COMMIT_MESSAGE)
+    }
+
+    /// Return the name of the remote that releases are pushed to
+    ///
+    /// The project level remote takes precedence over the global one.
+    pub fn remote<'a>(&'a self, project_settings: &'a ProjectSettings) -> &'a str {
+        project_settings
+            .remote
+            .as_deref()
+            .or(self.remote.as_deref())
+            .unwrap_or(DEFAULT_REMOTE)
+    }
+}
+
+impl GroupSettings {
+    /// Return the release commit message template of the group
+    ///
+    /// The group template takes precedence over the global one.
+    pub fn commit_message_template<'a>(&'a self, settings: &'a Settings) -> &'a str {
+        self.commit_message
+            .as_deref()
+            .or(settings.commit_message.as_deref())
+            .unwrap_or(DEFAULT_COMMIT_MESSAGE)
+    }
+
+    /// Return the name of the remote that group releases are pushed to
+    pub fn remote<'a>(&'a self, settings: &'a Settings) -> &'a str {
+        self.remote
+            .as_deref()
+            .or(settings.remote.as_deref())
+            .unwrap_or(DEFAULT_REMOTE)
+    }
+
+    /// Return the tag prefix, defaulting to `<group-name>-`
+    pub fn tag_prefix_for(&self, group_name: &str) -> String {
+        self.tag_prefix
+            .clone()
+            .unwrap_or_else(|| format!("{}-", group_name))
+    }
+
+    /// Return the tag name template
+    pub fn tag_format_template(&self) -> &str {
+        self.tag_format.as_deref().unwrap_or(DEFAULT_TAG_FORMAT)
+    }
+
+    /// Return the annotated tag message template
+    pub fn tag_message_template(&self) -> &str {
+        self.tag_message.as_deref().unwrap_or(DEFAULT_TAG_MESSAGE)
+    }
 }
 
 impl ProjectSettings {
@@ -68,6 +514,7 @@ impl ProjectSettings {
             ProjectType::Helm => self.project_path.join("Chart.yaml"),
             ProjectType::Node => self.project_path.join("package.json"),
             ProjectType::Rust => self.project_path.join("Cargo.toml"),
+            ProjectType::Exec => Err(anyhow::anyhow!("Exec project requires a manifest path"))?,
             ProjectType::Toml => Err(anyhow::anyhow!("TOML project requires a manifest path"))?,
             ProjectType::Versionfile => Err(anyhow::anyhow!(
                 "Versionfile project requires a manifest path"
@@ -76,4 +523,891 @@ impl ProjectSettings {
         };
         Ok(path.strip_prefix("./").unwrap_or(&path).to_path_buf())
     }
+
+    /// Check the settings that deserialization does not check
+    ///
+    /// These are the ignore rules and the commands of `exec` projects and
+    /// dependents.
+    pub(crate) fn check(&self) -> Result<()> {
+        self.ignore_rules()?;
+        self.check_command()?;
+        for dependent in self.dependents.iter().flatten() {
+            if matches!(dependent.dependent_type, DependentType::Exec)
+                && dependent.command.is_none()
+            {
+                return Err(anyhow::anyhow!(
+                    "Command is required for exec dependent {}",
+                    dependent.dependent_path.display()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that an `exec` project has a command
+    pub(crate) fn check_command(&self) -> Result<()> {
+        match (&self.project_type, &self.command) {
+            (ProjectType::Exec, None) => {
+                Err(anyhow::anyhow!("Command is required for an exec project"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Compile the ignore rules of the project
+    pub(crate) fn ignore_rules(&self) -> Result<IgnoreRules> {
+        IgnoreRules::new(self.ignore.as_deref().unwrap_or_default())
+    }
+
+    /// Return the tag prefix, defaulting to `<project-name>-`
+    pub fn tag_prefix_for(&self, project_name: &str) -> String {
+        self.tag_prefix
+            .clone()
+            .unwrap_or_else(|| format!("{}-", project_name))
+    }
+
+    /// Return the tag name template
+    pub fn tag_format_template(&self) -> &str {
+        self.tag_format.as_deref().unwrap_or(DEFAULT_TAG_FORMAT)
+    }
+
+    /// Return the annotated tag message template
+    pub fn tag_message_template(&self) -> &str {
+        self.tag_message.as_deref().unwrap_or(DEFAULT_TAG_MESSAGE)
+    }
+
+    /// Return the paths that must not have uncommitted changes on release
+    pub fn dirty_check_paths(&self) -> Result<Vec<PathBuf>> {
+        match self.dirty_check {
+            DirtyCheck::Project => self.release_paths(),
+            _ => Ok(vec![self.get_manifest_file_path()?]),
+        }
+    }
+
+    /// Return the paths a release of the project writes to
+    ///
+    /// These are the manifest, the project path, the changelog and the
+    /// dependent paths. A dependent glob is covered by the directory it
+    /// starts from.
+    pub fn release_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = vec![self.get_manifest_file_path()?, self.project_path.clone()];
+        paths.extend(self.changelog.clone());
+        paths.extend(self.dependents.iter().flatten().map(|dependent| {
+            match glob::is_glob(&dependent.dependent_path) {
+                true => glob::base_dir(&dependent.dependent_path),
+                false => dependent.dependent_path.clone(),
+            }
+        }));
+        Ok(paths)
+    }
+}
+
+/// Prefix of the environment variables that override settings
+///
+/// It is separate from the `MONOVERSE_*` variables that hooks receive, so
+/// that monoverse can be called from a hook.
+const ENVIRONMENT_PREFIX: &str = "MONOVERSE_CONFIG";
+
+/// Environment variables that override settings
+///
+/// `MONOVERSE_CONFIG__REMOTE` sets `remote`, and a double underscore
+/// separates nested keys, as in `MONOVERSE_CONFIG__PROJECTS__API__TAG_PREFIX`.
+fn environment() -> config::Environment {
+    config::Environment::with_prefix(ENVIRONMENT_PREFIX)
+        .prefix_separator("__")
+        .separator("__")
+}
+
+/// Return the environment variable that set a key or one of its parents
+fn environment_variable(key: &str, overrides: &[String]) -> Option<String> {
+    overrides
+        .iter()
+        .find(|name| key == *name || key.starts_with(&format!("{}.", name)))
+        .map(|name| {
+            format!(
+                "{}__{}",
+                ENVIRONMENT_PREFIX,
+                name.replace('.', "__").to_uppercase()
+            )
+        })
+}
+
+/// Find the project files in the repository, skipping files ignored by git
+///
+/// Without `globs` every file named [`PROJECT_FILE_NAME`] is found. With
+/// them, only the directories the globs start from are searched and a file
+/// is found if any glob matches it. Returns the paths relative to the
+/// repository root, sorted.
+fn discover_project_files(repo_path: &Path, globs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let matchers = globs
+        .iter()
+        .map(|pattern| glob::matcher(pattern))
+        .collect::<Result<Vec<_>>>()?;
+    let bases = match globs.is_empty() {
+        true => vec![repo_path.to_path_buf()],
+        false => globs
+            .iter()
+            .map(|pattern| repo_path.join(glob::base_dir(pattern)))
+            .filter(|base| base.is_dir())
+            .collect(),
+    };
+    let mut files = Vec::new();
+    for base in bases {
+        for entry in ::ignore::WalkBuilder::new(&base).build() {
+            let entry = entry?;
+            if !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+            {
+                continue;
+            }
+            let path = entry.path().strip_prefix(repo_path)?;
+            let found = match matchers.is_empty() {
+                true => entry.file_name() == PROJECT_FILE_NAME,
+                false => matchers.iter().any(|matcher| matcher.is_match(path)),
+            };
+            if found {
+                files.push(path.to_path_buf());
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Add the projects from project files to the configuration
+///
+/// A project is named after its directory unless the file sets `name`, and
+/// `path` defaults to the directory. Projects defined more than once are
+/// reported together. Returns the file of each added project.
+fn add_project_files(
+    value: &mut Value,
+    config_file: &Path,
+    repo_path: &Path,
+    files: &[PathBuf],
+) -> Result<HashMap<String, PathBuf>> {
+    let mut sources = HashMap::new();
+    if files.is_empty() {
+        return Ok(sources);
+    }
+    let projects = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("{}: expected a map", config_file.display()))?
+        .entry("projects")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("{}: 'projects' must be a map", config_file.display()))?;
+    let mut collisions = Vec::new();
+    for file in files {
+        let display_path = repo_path.join(file);
+        let content = crate::io::read_file(file, &repo_path.to_path_buf())?;
+        let mut project: Value = serde_yaml::from_str(&content)
+            .with_context(|| format!("Could not parse {}", display_path.display()))?;
+        let Value::Object(settings) = &mut project else {
+            return Err(anyhow::anyhow!(
+                "{}: expected a map of project settings",
+                display_path.display()
+            ));
+        };
+        let dir = file.parent().unwrap_or(Path::new(""));
+        let name = match settings.remove("name") {
+            Some(Value::String(name)) => name,
+            Some(_) => {
+                return Err(anyhow::anyhow!(
+                    "{}: 'name' must be a string",
+                    display_path.display()
+                ))
+            }
+            None => dir
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "{}: a project file at the repository root must set 'name'",
+                        display_path.display()
+                    )
+                })?,
+        };
+        settings
+            .entry("path")
+            .or_insert_with(|| match dir == Path::new("") {
+                true => Value::String(".".to_string()),
+                false => Value::String(dir.to_string_lossy().to_string()),
+            });
+        if let Some(existing) = sources.get(&name) {
+            collisions.push(format!(
+                "Project '{}' is defined in both {} and {}",
+                name,
+                existing.display(),
+                display_path.display()
+            ));
+        } else if projects.contains_key(&name) {
+            collisions.push(format!(
+                "Project '{}' is defined in both {} and {}",
+                name,
+                config_file.display(),
+                display_path.display()
+            ));
+        }
+        projects.insert(name.clone(), project);
+        sources.insert(name, display_path);
+    }
+    if !collisions.is_empty() {
+        return Err(anyhow::anyhow!(collisions.join("\n")));
+    }
+    Ok(sources)
+}
+
+/// The source of a project key that was not set in the project itself,
+/// keyed by project name and key
+type Origins = HashMap<(String, String), String>;
+
+/// Merge `defaults` and the templates a project `extends` into each project
+///
+/// Keys are replaced as a whole, first from `defaults`, then from each
+/// template in the listed order and finally from the project itself.
+/// Returns where the inherited keys of each project came from.
+fn apply_templates(value: &mut Value) -> Result<Origins> {
+    let mut origins = Origins::new();
+    let Some(root) = value.as_object_mut() else {
+        return Ok(origins);
+    };
+    let defaults = match root.remove("defaults") {
+        Some(Value::Object(defaults)) => defaults,
+        Some(_) => return Err(anyhow::anyhow!("'defaults' must be a map")),
+        None => Map::new(),
+    };
+    let templates = match root.remove("templates") {
+        Some(Value::Object(templates)) => templates,
+        Some(_) => return Err(anyhow::anyhow!("'templates' must be a map")),
+        None => Map::new(),
+    };
+    let mut template_maps = HashMap::new();
+    for (name, template) in &templates {
+        let Value::Object(template) = template else {
+            return Err(anyhow::anyhow!("Template '{}' must be a map", name));
+        };
+        if template.contains_key("extends") {
+            return Err(anyhow::anyhow!(
+                "Template '{}' cannot extend other templates",
+                name
+            ));
+        }
+        template_maps.insert(name.as_str(), template);
+    }
+    let Some(Value::Object(projects)) = root.get_mut("projects") else {
+        return Ok(origins);
+    };
+    for (project, settings) in projects.iter_mut() {
+        let Value::Object(own) = settings else {
+            continue;
+        };
+        let extends = match own.remove("extends") {
+            None => Vec::new(),
+            Some(Value::String(name)) => vec![name],
+            Some(Value::Array(names)) => names
+                .into_iter()
+                .map(|name| match name {
+                    Value::String(name) => Ok(name),
+                    _ => Err(anyhow::anyhow!(
+                        "'extends' of project '{}' must be a template name or a list of them",
+                        project
+                    )),
+                })
+                .collect::<Result<_>>()?,
+            Some(_) => {
+                return Err(anyhow::anyhow!(
+                    "'extends' of project '{}' must be a template name or a list of them",
+                    project
+                ))
+            }
+        };
+        let mut merged = Map::new();
+        let mut inherit = |source: String, template: &Map<String, Value>| {
+            for (key, value) in template {
+                merged.insert(key.clone(), value.clone());
+                origins.insert((project.clone(), key.clone()), source.clone());
+            }
+        };
+        inherit("defaults".to_string(), &defaults);
+        for name in extends {
+            let template = template_maps.get(name.as_str()).ok_or_else(|| {
+                let message = format!("Project '{}' extends unknown template '{}'", project, name);
+                match suggest(&name, template_maps.keys().copied()) {
+                    Some(suggestion) => {
+                        anyhow::anyhow!("{}, did you mean '{}'?", message, suggestion)
+                    }
+                    None => anyhow::anyhow!(message),
+                }
+            })?;
+            inherit(format!("templates.{}", name), template);
+        }
+        for (key, value) in std::mem::take(own) {
+            origins.remove(&(project.clone(), key.clone()));
+            merged.insert(key, value);
+        }
+        *own = merged;
+    }
+    Ok(origins)
+}
+
+/// Check the keys of `defaults` and of the templates that no project uses
+///
+/// Templates that a project extends are checked with the merged settings of
+/// the project. The values of unused ones are not checked, since they may
+/// only be valid together with the settings of a project, such as its
+/// `type`.
+fn check_unused_templates(value: &Value) -> Result<()> {
+    let projects = value.get("projects").and_then(Value::as_object);
+    let used = projects
+        .into_iter()
+        .flat_map(Map::values)
+        .filter_map(|project| project.get("extends"))
+        .flat_map(|extends| match extends {
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            name => name.as_str().into_iter().collect::<Vec<_>>(),
+        })
+        .collect::<HashSet<_>>();
+    let mut unused = Vec::new();
+    if projects.is_none_or(Map::is_empty) {
+        if let Some(Value::Object(defaults)) = value.get("defaults") {
+            unused.push(("defaults".to_string(), defaults));
+        }
+    }
+    if let Some(Value::Object(templates)) = value.get("templates") {
+        for (name, template) in templates {
+            if let (false, Value::Object(template)) = (used.contains(name.as_str()), template) {
+                unused.push((format!("templates.{}", name), template));
+            }
+        }
+    }
+    let schema = schemars::schema_for!(ProjectSettings);
+    let known = schema
+        .schema
+        .object
+        .as_ref()
+        .map(|object| {
+            object
+                .properties
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    for (location, template) in unused {
+        let Some(key) = template.keys().find(|key| !known.contains(&key.as_str())) else {
+            continue;
+        };
+        return Err(match suggest(key, known.iter().copied()) {
+            Some(suggestion) => anyhow::anyhow!(
+                "unknown key '{}' at '{}.{}', did you mean '{}'?",
+                key,
+                location,
+                key,
+                suggestion
+            ),
+            None => anyhow::anyhow!(
+                "unknown key '{}' at '{}.{}', expected one of: {}",
+                key,
+                location,
+                key,
+                known.join(", ")
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Turn a deserialization error into a message that points to the problem
+///
+/// The message names the file, the project and the full key path, and the
+/// template for keys inherited from one. Unknown keys get the closest known
+/// key as a suggestion.
+fn describe_error(
+    file: &Path,
+    path: &serde_path_to_error::Path,
+    message: &str,
+    origins: &Origins,
+    project_files: &HashMap<String, PathBuf>,
+    overrides: &[String],
+) -> anyhow::Error {
+    let segments = path.iter().collect::<Vec<_>>();
+    let file = match segments.as_slice() {
+        [Segment::Map { key }, Segment::Map { key: project }, ..] if key == "projects" => {
+            project_files.get(project).map_or(file, PathBuf::as_path)
+        }
+        _ => file,
+    };
+    let unknown_field = Regex::new(r"^unknown field `([^`]*)`, expected (.*)$").unwrap();
+    let captures = unknown_field.captures(message);
+    let key = segments
+        .iter()
+        .filter_map(|segment| match segment {
+            Segment::Seq { index } => Some(index.to_string()),
+            Segment::Map { key } => Some(key.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(".");
+    let source = match environment_variable(&key, overrides) {
+        Some(variable) => format!("environment variable {}", variable),
+        None => file.display().to_string(),
+    };
+    let location = match segments.as_slice() {
+        [Segment::Map { key }, Segment::Map { key: project }, Segment::Map { key: field }, ..]
+            if key == "projects" && origins.contains_key(&(project.clone(), field.clone())) =>
+        {
+            format!(
+                " in '{}' inherited by project '{}' at '{}'",
+                origins[&(project.clone(), field.clone())],
+                project,
+                path
+            )
+        }
+        [Segment::Map { key }, Segment::Map { key: project }, ..] if key == "projects" => {
+            format!(" in project '{}' at '{}'", project, path)
+        }
+        [] | [_] => String::new(),
+        _ => format!(" at '{}'", path),
+    };
+    let Some(captures) = captures else {
+        return anyhow::anyhow!("{}: {}{}", source, message, location);
+    };
+    let key = &captures[1];
+    let expected = Regex::new(r"`([^`]*)`")
+        .unwrap()
+        .captures_iter(&captures[2])
+        .map(|captures| captures[1].to_string())
+        .collect::<Vec<_>>();
+    match suggest(key, expected.iter().map(String::as_str)) {
+        Some(suggestion) => anyhow::anyhow!(
+            "{}: unknown key '{}'{}, did you mean '{}'?",
+            source,
+            key,
+            location,
+            suggestion
+        ),
+        None => anyhow::anyhow!(
+            "{}: unknown key '{}'{}, expected one of: {}",
+            source,
+            key,
+            location,
+            expected.join(", ")
+        ),
+    }
+}
+
+/// Return the candidate most similar to the given name, if any is close
+fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .map(|candidate| (strsim::jaro_winkler(name, candidate), candidate))
+        .filter(|(similarity, _)| *similarity > 0.8)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate)
+}
+
+fn schema_from_json(value: Value) -> Schema {
+    serde_json::from_value(value).expect("valid JSON Schema")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Settings {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_commit_message_template() {
+        let settings = parse(
+            r#"
+commit_message: "release: {project} {version}"
+projects:
+  api:
+    type: rust
+    commit_message: "release(api): {version} [skip ci]"
+  web:
+    type: node
+"#,
+        );
+        let api = settings.project_settings("api").unwrap();
+        let web = settings.project_settings("web").unwrap();
+        assert_eq!(
+            settings.commit_message_template(api),
+            "release(api): {version} [skip ci]"
+        );
+        assert_eq!(
+            settings.commit_message_template(web),
+            "release: {project} {version}"
+        );
+    }
+
+    #[test]
+    fn test_default_commit_message_template() {
+        let settings = parse("projects:\n  api:\n    type: rust\n");
+        let api = settings.project_settings("api").unwrap();
+        assert_eq!(
+            settings.commit_message_template(api),
+            DEFAULT_COMMIT_MESSAGE
+        );
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = serde_json::to_value(Settings::json_schema()).unwrap();
+        assert_eq!(schema["required"], serde_json::json!(["projects"]));
+        let project_types = schema["definitions"]["ProjectType"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| variant["enum"][0].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            project_types,
+            vec![
+                "exec",
+                "helm",
+                "node",
+                "rust",
+                "toml",
+                "versionfile",
+                "yaml"
+            ]
+        );
+        let dependent = &schema["definitions"]["DependentSettings"]["properties"];
+        assert!(dependent["type"].is_object());
+        assert!(dependent["replace"].is_object());
+        assert!(schema["properties"]["templates"].is_object());
+        assert!(schema["definitions"]["ProjectSettings"]["properties"]["extends"].is_object());
+        assert!(schema["definitions"]["ProjectTemplate"]["properties"]["extends"].is_null());
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("monoverse.yaml");
+        std::fs::write(
+            &config_path,
+            "projects:\n  api:\n    type: rust\n    dependants: []\n",
+        )
+        .unwrap();
+        let error = Settings::new(dir.path(), None).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            format!(
+                "{}: unknown key 'dependants' in project 'api' at 'projects.api.dependants', did you mean 'dependents'?",
+                config_path.display()
+            )
+        );
+
+        std::fs::write(&config_path, "projects: {}\nfoo: bar\n").unwrap();
+        let error = Settings::new(dir.path(), None).unwrap_err().to_string();
+        assert!(error.ends_with(
+            "unknown key 'foo', expected one of: projects, commit_message, remote, groups, project_files"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_ignore_rules() {
+        let error = from_yaml(
+            "projects:\n  api:\n    type: rust\n    ignore:\n      - message: '(unclosed'\n",
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("monoverse.yaml: Invalid ignore pattern: (unclosed: "));
+        assert!(error.to_string().ends_with(" in project 'api'"));
+    }
+
+    #[test]
+    fn test_missing_exec_command() {
+        let error = from_yaml(
+            "projects:\n  api:\n    type: exec\n    path: api\n    manifest_path: api/VERSION\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "monoverse.yaml: Command is required for an exec project in project 'api'"
+        );
+        let error = from_yaml(
+            "projects:\n  api:\n    type: rust\n    dependents:\n      - type: exec\n        path: VERSION\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "monoverse.yaml: Command is required for exec dependent VERSION in project 'api'"
+        );
+    }
+
+    fn from_yaml(yaml: &str) -> Result<Settings> {
+        Settings::from_value(
+            serde_yaml::from_str(yaml).unwrap(),
+            Path::new("monoverse.yaml"),
+            &HashMap::new(),
+            &[],
+        )
+    }
+
+    #[test]
+    fn test_templates() {
+        let settings = from_yaml(
+            r#"
+defaults:
+  tag_format: "{project}/v{version}"
+  scheme: semver
+templates:
+  service:
+    type: helm
+    dependents:
+      - type: yaml
+        path: deploy/values.yaml
+        selector: image.tag
+  calver:
+    scheme: calver
+projects:
+  api:
+    extends: service
+    path: services/api
+  web:
+    extends: [service, calver]
+    path: services/web
+    tag_format: "web-{version}"
+  lib:
+    type: rust
+"#,
+        )
+        .unwrap();
+        let api = settings.project_settings("api").unwrap();
+        assert_eq!(api.project_type, ProjectType::Helm);
+        assert_eq!(api.scheme, VersionScheme::Semver);
+        assert_eq!(api.tag_format_template(), "{project}/v{version}");
+        assert_eq!(api.dependents.as_ref().unwrap().len(), 1);
+        let web = settings.project_settings("web").unwrap();
+        assert_eq!(web.scheme, VersionScheme::Calver);
+        assert_eq!(web.tag_format_template(), "web-{version}");
+        let lib = settings.project_settings("lib").unwrap();
+        assert_eq!(lib.project_type, ProjectType::Rust);
+        assert_eq!(lib.scheme, VersionScheme::Semver);
+        assert!(lib.dependents.is_none());
+    }
+
+    #[test]
+    fn test_template_errors() {
+        let error = from_yaml(
+            "templates:\n  service:\n    type: helm\nprojects:\n  api:\n    extends: servce\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "monoverse.yaml: Project 'api' extends unknown template 'servce', did you mean 'service'?"
+        );
+        let error = from_yaml(
+            "templates:\n  service:\n    type: helm\n    tagprefix: x\nprojects:\n  api:\n    extends: service\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "monoverse.yaml: unknown key 'tagprefix' in 'templates.service' inherited by project 'api' at 'projects.api.tagprefix', did you mean 'tag_prefix'?"
+        );
+        let error = from_yaml(
+            "templates:\n  unused:\n    tagprefix: x\nprojects:\n  api:\n    type: rust\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "monoverse.yaml: unknown key 'tagprefix' at 'templates.unused.tagprefix', did you mean 'tag_prefix'?"
+        );
+        // Unused templates are only checked for unknown keys
+        from_yaml(
+            "defaults:\n  scheme: weekly\ntemplates:\n  tool:\n    type: exec\nprojects: {}\n",
+        )
+        .unwrap();
+        let error = from_yaml("defaults:\n  sheme: semver\nprojects: {}\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "monoverse.yaml: unknown key 'sheme' at 'defaults.sheme', did you mean 'scheme'?"
+        );
+        // Used templates are checked with the settings of the project
+        let error = from_yaml(
+            "templates:\n  tool:\n    type: exec\n    manifest_path: cli/VERSION\nprojects:\n  cli:\n    extends: tool\n    path: cli\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "monoverse.yaml: Command is required for an exec project in project 'cli'"
+        );
+    }
+
+    #[test]
+    fn test_project_files() {
+        let (dir, _repo) = crate::testing::init_repo();
+        let config = "templates:\n  service:\n    type: node\nprojects:\n  lib:\n    type: rust\n";
+        crate::testing::write(dir.path(), "monoverse.yaml", config);
+        crate::testing::write(
+            dir.path(),
+            "services/api/monoverse.project.yaml",
+            "extends: service\n",
+        );
+        crate::testing::write(
+            dir.path(),
+            "services/web/monoverse.project.yaml",
+            "name: frontend\ntype: node\ntag_prefix: web-\n",
+        );
+        crate::testing::write(dir.path(), ".gitignore", "vendor/\n");
+        crate::testing::write(
+            dir.path(),
+            "vendor/lib/monoverse.project.yaml",
+            "type: rust\n",
+        );
+        let settings = Settings::new(dir.path(), None).unwrap();
+        let mut names = settings.projects.keys().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["api", "frontend", "lib"]);
+        let api = settings.project_settings("api").unwrap();
+        assert_eq!(api.project_type, ProjectType::Node);
+        assert_eq!(api.project_path, PathBuf::from("services/api"));
+        let frontend = settings.project_settings("frontend").unwrap();
+        assert_eq!(frontend.project_path, PathBuf::from("services/web"));
+
+        crate::testing::write(
+            dir.path(),
+            "tools/cli/monoverse.project.yaml",
+            "type: rust\n",
+        );
+        crate::testing::write(
+            dir.path(),
+            "monoverse.yaml",
+            &format!(
+                "project_files: ['services/*/monoverse.project.yaml', 'libs/*/monoverse.project.yaml']\n{}",
+                config
+            ),
+        );
+        let settings = Settings::new(dir.path(), None).unwrap();
+        let mut names = settings.projects.keys().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["api", "frontend", "lib"]);
+
+        crate::testing::write(
+            dir.path(),
+            "libs/lib/monoverse.project.yaml",
+            "type: rust\ntagprefix: x\n",
+        );
+        let error = Settings::new(dir.path(), None).unwrap_err().to_string();
+        assert!(error.starts_with("Project 'lib' is defined in both "));
+        assert!(error.ends_with("libs/lib/monoverse.project.yaml"));
+
+        crate::testing::write(
+            dir.path(),
+            "libs/lib/monoverse.project.yaml",
+            "name: other\ntype: rust\ntagprefix: x\n",
+        );
+        let error = Settings::new(dir.path(), None).unwrap_err().to_string();
+        assert_eq!(
+            error,
+            format!(
+                "{}: unknown key 'tagprefix' in project 'other' at 'projects.other.tagprefix', did you mean 'tag_prefix'?",
+                dir.path().join("libs/lib/monoverse.project.yaml").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_config_file_and_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        crate::testing::write(
+            dir.path(),
+            "config/release.yaml",
+            "remote: origin\nprojects:\n  api:\n    type: rust\n    path: api\n",
+        );
+        let load = |variables: &[(&str, &str)]| {
+            let variables = variables
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            Settings::from_sources(
+                dir.path(),
+                Some(&dir.path().join("config/release.yaml")),
+                environment().source(Some(variables)),
+            )
+        };
+        let settings = load(&[
+            ("MONOVERSE_CONFIG__REMOTE", "upstream"),
+            ("MONOVERSE_CONFIG__PROJECTS__API__TAG_PREFIX", "api/v"),
+            ("OTHER_REMOTE", "ignored"),
+            // Set for hooks, which may call monoverse
+            ("MONOVERSE_PROJECT", "api"),
+            ("MONOVERSE_VERSION", "1.2.3"),
+            ("MONOVERSE_PREVIOUS_VERSION", "1.2.2"),
+            ("MONOVERSE_TAG", "api-1.2.3"),
+        ])
+        .unwrap();
+        let api = settings.project_settings("api").unwrap();
+        assert_eq!(settings.remote(api), "upstream");
+        assert_eq!(api.tag_prefix_for("api"), "api/v");
+        assert_eq!(api.project_path, PathBuf::from("api"));
+
+        let error = load(&[("MONOVERSE_CONFIG__PROJECTS__API__TAG_PREFX", "api/v")]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "environment variable MONOVERSE_CONFIG__PROJECTS__API__TAG_PREFX: unknown key 'tag_prefx' in project 'api' at 'projects.api.tag_prefx', did you mean 'tag_prefix'?"
+        );
+        let error = load(&[("MONOVERSE_CONFIG__PROJECTS__API__SCHEME", "dated")]).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("environment variable MONOVERSE_CONFIG__PROJECTS__API__SCHEME: "));
+
+        let error = Settings::new(dir.path(), None).unwrap_err();
+        assert!(error.to_string().starts_with("No configuration found in "));
+    }
+
+    #[test]
+    fn test_groups() {
+        let projects = r#"
+projects:
+  api:
+    type: rust
+    path: api
+  worker:
+    type: rust
+    path: worker
+  web:
+    type: node
+    path: web
+    scheme: semver
+"#;
+        let settings = from_yaml(&format!(
+            "{}groups:\n  backend:\n    members: [api, worker]\n",
+            projects
+        ))
+        .unwrap();
+        assert_eq!(settings.group_of("worker"), Some("backend"));
+        assert_eq!(settings.group_of("web"), None);
+        let group = settings.group_settings("backend").unwrap();
+        assert_eq!(group.tag_prefix_for("backend"), "backend-");
+        assert_eq!(group.remote(&settings), DEFAULT_REMOTE);
+
+        let error = |groups: &str| {
+            from_yaml(&format!("{}groups:\n{}", projects, groups))
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error("  backend:\n    members: [api, cron]\n"),
+            "monoverse.yaml: Group 'backend' has an unknown member 'cron'"
+        );
+        assert_eq!(
+            error("  a:\n    members: [api]\n  b:\n    members: [api, worker]\n"),
+            "monoverse.yaml: Project 'api' is a member of both group 'a' and group 'b'"
+        );
+        assert_eq!(
+            error("  api:\n    members: [worker]\n"),
+            "monoverse.yaml: Group 'api' has the same name as a project"
+        );
+        assert_eq!(
+            error("  all:\n    members: [api, web]\n"),
+            "monoverse.yaml: Members of group 'all' use different version schemes"
+        );
+    }
 }
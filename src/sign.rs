@@ -0,0 +1,148 @@
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, Result};
+use git2::{Config, Repository};
+
+/// Signature format, mirroring the `gpg.format` git configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningFormat {
+    OpenPgp,
+    Ssh,
+}
+
+/// Signs commit and tag payloads the same way git does
+///
+/// OpenPGP signatures are created with `gpg` and SSH signatures with
+/// `ssh-keygen -Y sign`. The programs can be overridden with the
+/// `gpg.program` and `gpg.ssh.program` git configuration.
+#[derive(Debug, Clone)]
+pub struct Signer {
+    format: SigningFormat,
+    program: String,
+    key: String,
+}
+
+impl Signer {
+    /// Create a signer from `gpg.format` and `user.signingkey`
+    ///
+    /// For OpenPGP the committer identity is used when no signing key is
+    /// configured. SSH signing requires `user.signingkey` to point at a key
+    /// file.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let format = match config.get_string("gpg.format") {
+            Ok(format) => match format.as_str() {
+                "openpgp" => SigningFormat::OpenPgp,
+                "ssh" => SigningFormat::Ssh,
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "Unsupported signing format 'gpg.format={}'",
+                        format
+                    ))
+                }
+            },
+            Err(_) => SigningFormat::OpenPgp,
+        };
+        let key = config.get_string("user.signingkey").ok();
+        match format {
+            SigningFormat::OpenPgp => {
+                let key = match key {
+                    Some(key) => key,
+                    None => format!(
+                        "{} <{}>",
+                        config.get_string("user.name")?,
+                        config.get_string("user.email")?
+                    ),
+                };
+                Ok(Self {
+                    format,
+                    program: config
+                        .get_string("gpg.openpgp.program")
+                        .or_else(|_| config.get_string("gpg.program"))
+                        .unwrap_or_else(|_| "gpg".to_string()),
+                    key,
+                })
+            }
+            SigningFormat::Ssh => {
+                let key = key.ok_or_else(|| {
+                    anyhow::anyhow!("SSH signing requires 'user.signingkey' to be set")
+                })?;
+                if key.starts_with("key::") || key.starts_with("ssh-") {
+                    return Err(anyhow::anyhow!(
+                        "SSH signing requires 'user.signingkey' to be a path to a key file"
+                    ));
+                }
+                Ok(Self {
+                    format,
+                    program: config
+                        .get_string("gpg.ssh.program")
+                        .unwrap_or_else(|_| "ssh-keygen".to_string()),
+                    key: expand_home(&key).display().to_string(),
+                })
+            }
+        }
+    }
+
+    /// Return a detached, armored signature for the payload
+    pub fn sign(&self, payload: &str) -> Result<String> {
+        let mut command = Command::new(&self.program);
+        match self.format {
+            SigningFormat::OpenPgp => command.args(["--status-fd=2", "-bsau", &self.key]),
+            SigningFormat::Ssh => command.args(["-Y", "sign", "-n", "git", "-f", &self.key]),
+        };
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run '{}' for signing", self.program))?;
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Failed to open stdin of '{}'", self.program))?
+            .write_all(payload.as_bytes())?;
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!(
+                "Signing with '{}' failed: {}",
+                self.program,
+                stderr.trim()
+            ));
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+/// Return a signer for release commits
+///
+/// Commits are signed when `force` is set or `commit.gpgsign` is enabled.
+pub fn commit_signer(repo: &Repository, force: bool) -> Result<Option<Signer>> {
+    signer_for(repo, "commit.gpgsign", force)
+}
+
+/// Return a signer for release tags
+///
+/// Annotated tags are signed when `force` is set or `tag.gpgsign` is enabled.
+pub fn tag_signer(repo: &Repository, force: bool) -> Result<Option<Signer>> {
+    signer_for(repo, "tag.gpgsign", force)
+}
+
+fn signer_for(repo: &Repository, key: &str, force: bool) -> Result<Option<Signer>> {
+    let config = repo.config()?;
+    if force || config.get_bool(key).unwrap_or(false) {
+        Ok(Some(Signer::from_config(&config)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+/// File contents staged in memory and written together
+///
+/// Paths are relative to the repository root. Reads return the staged
+/// content if there is one, so several edits to the same file build on each
+/// other. Nothing is written until [`Staging::apply`].
+#[derive(Debug)]
+pub struct Staging {
+    repo_path: PathBuf,
+    files: Vec<(PathBuf, String)>,
+}
+
+/// Original contents of the files written by [`Staging::apply`]
+#[derive(Debug)]
+pub struct Backup {
+    /// Absolute paths with the original content, None if the file was new
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+}
+
+impl Staging {
+    pub fn new<P: AsRef<Path>>(repo_path: P) -> Self {
+        Self {
+            repo_path: repo_path.as_ref().to_path_buf(),
+            files: Vec::new(),
+        }
+    }
+
+    pub fn repo_path(&self) -> &Path {
+        &self.repo_path
+    }
+
+    /// Read a file, preferring the staged content over the one on disk
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<String> {
+        let path = path.as_ref();
+        match self.staged(path) {
+            Some(content) => Ok(content.to_string()),
+            None => crate::io::read_file(path, &self.repo_path),
+        }
+    }
+
+    /// Stage new content for a file
+    pub fn write<P: AsRef<Path>>(&mut self, path: P, content: String) {
+        let path = path.as_ref();
+        match self.files.iter_mut().find(|(staged, _)| staged == path) {
+            Some((_, staged)) => *staged = content,
+            None => self.files.push((path.to_path_buf(), content)),
+        }
+    }
+
+    /// Return the staged content of a file
+    pub fn staged<P: AsRef<Path>>(&self, path: P) -> Option<&str> {
+        let path = path.as_ref();
+        self.files
+            .iter()
+            .find(|(staged, _)| staged == path)
+            .map(|(_, content)| content.as_str())
+    }
+
+    /// Return the staged paths in the order they were first staged
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|(path, _)| path.clone()).collect()
+    }
+
+    /// Write every staged file
+    ///
+    /// Each file is written to a temporary file next to it and renamed over
+    /// the original, so a file is never left partially written. If a write
+    /// fails, the files written so far are restored before returning the
+    /// error. Otherwise the returned backup can restore all of them.
+    pub fn apply(self) -> Result<Backup> {
+        let mut backup = Backup { files: Vec::new() };
+        for (path, content) in &self.files {
+            let path = self.repo_path.join(path);
+            let written = read_original(&path).and_then(|original| {
+                write_atomic(&path, content.as_bytes())?;
+                Ok(original)
+            });
+            match written {
+                Ok(original) => backup.files.push((path, original)),
+                Err(e) => {
+                    backup.restore_after(&e);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(backup)
+    }
+}
+
+impl Backup {
+    /// Add files changed outside the staging, with paths from the repository root
+    ///
+    /// They are restored before the staged files.
+    pub fn extend<I>(&mut self, repo_path: &Path, files: I)
+    where
+        I: IntoIterator<Item = (PathBuf, Option<Vec<u8>>)>,
+    {
+        self.files.extend(
+            files
+                .into_iter()
+                .map(|(path, original)| (repo_path.join(path), original)),
+        );
+    }
+
+    /// Put the original contents back and remove the files that were new
+    pub fn restore(self) -> Result<()> {
+        for (path, original) in self.files.into_iter().rev() {
+            match original {
+                Some(content) => write_atomic(&path, &content)?,
+                None => fs::remove_file(&path)
+                    .with_context(|| format!("Could not remove file at: {}", path.display()))?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Restore the files after a failure, logging restore errors
+    ///
+    /// The original error is the one worth returning, so a failed restore
+    /// is only logged.
+    pub fn restore_after(self, error: &anyhow::Error) {
+        log::warn!("Restoring the original files after an error: {:#}", error);
+        if let Err(e) = self.restore() {
+            log::error!("Could not restore the original files: {:#}", e);
+        }
+    }
+}
+
+/// Read the content of a file before it is written, None if it is new
+fn read_original(path: &Path) -> Result<Option<Vec<u8>>> {
+    match path.exists() {
+        true => Ok(Some(fs::read(path).with_context(|| {
+            format!("Could not read file at: {}", path.display())
+        })?)),
+        false => Ok(None),
+    }
+}
+
+/// Write a file through a temporary file and a rename
+///
+/// The permissions of an existing file are kept.
+fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid file path: {}", path.display()))?;
+    let temp_path = path.with_file_name(format!(".{}.monoverse", file_name.to_string_lossy()));
+    let result = fs::write(&temp_path, content)
+        .and_then(|_| match fs::metadata(path) {
+            Ok(metadata) => fs::set_permissions(&temp_path, metadata.permissions()),
+            Err(_) => Ok(()),
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result.with_context(|| format!("Could not write file at: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::write;
+
+    #[test]
+    fn test_apply_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.txt", "a");
+        let mut staging = Staging::new(dir.path());
+        staging.write("a.txt", staging.read("a.txt").unwrap() + "b");
+        staging.write("a.txt", staging.read("a.txt").unwrap() + "c");
+        staging.write("new.txt", "new".to_string());
+        assert_eq!(
+            staging.paths(),
+            vec![PathBuf::from("a.txt"), PathBuf::from("new.txt")]
+        );
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "a");
+
+        let backup = staging.apply().unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "abc");
+        assert_eq!(
+            fs::read_to_string(dir.path().join("new.txt")).unwrap(),
+            "new"
+        );
+        backup.restore().unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "a");
+        assert!(!dir.path().join("new.txt").exists());
+    }
+
+    #[test]
+    fn test_apply_failure_restores() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.txt", "a");
+        let mut staging = Staging::new(dir.path());
+        staging.write("a.txt", "changed".to_string());
+        staging.write("missing/b.txt", "b".to_string());
+        assert!(staging.apply().is_err());
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "a");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_read_failure_restores() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "a.txt", "a");
+        // A directory exists but cannot be read as a file
+        fs::create_dir(dir.path().join("b.txt")).unwrap();
+        let mut staging = Staging::new(dir.path());
+        staging.write("a.txt", "changed".to_string());
+        staging.write("b.txt", "b".to_string());
+        let error = staging.apply().unwrap_err();
+        assert!(error.to_string().starts_with("Could not read file at: "));
+        assert_eq!(fs::read_to_string(dir.path().join("a.txt")).unwrap(), "a");
+    }
+}
@@ -0,0 +1,205 @@
+use std::{collections::BTreeMap, iter::Peekable, str::Chars};
+
+use anyhow::Result;
+use regex::Regex;
+
+/// Values available to name and message templates
+///
+/// Templates reference values with `{name}` placeholders. `{{name}}` is a
+/// placeholder as well, as in the `replace` setting of regex dependents.
+/// Other literal braces are written as `{{` and `}}`.
+#[derive(Debug, Default, Clone)]
+pub struct TemplateContext {
+    values: BTreeMap<&'static str, String>,
+}
+
+impl TemplateContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a value to the context, replacing any previous value
+    pub fn with<T: ToString>(mut self, name: &'static str, value: T) -> Self {
+        self.values.insert(name, value.to_string());
+        self
+    }
+
+    /// Render a template, failing on unknown placeholders
+    pub fn render(&self, template: &str) -> Result<String> {
+        let mut rendered = String::with_capacity(template.len());
+        for token in parse(template)? {
+            match token {
+                Token::Literal(literal) => rendered.push_str(&literal),
+                Token::Placeholder(name) => {
+                    let value = self.values.get(name.as_str()).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Unknown placeholder '{{{}}}' in template '{}', available placeholders: {}",
+                            name,
+                            template,
+                            self.placeholders()
+                        )
+                    })?;
+                    rendered.push_str(value);
+                }
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// Build a regular expression that matches the rendered template
+    ///
+    /// Placeholders with a value in the context match that value, any other
+    /// placeholder matches arbitrary text.
+    pub fn to_regex(&self, template: &str) -> Result<Regex> {
+        let mut pattern = String::from("(?s)^");
+        for token in parse(template)? {
+            match token {
+                Token::Literal(literal) => pattern.push_str(&regex::escape(&literal)),
+                Token::Placeholder(name) => match self.values.get(name.as_str()) {
+                    Some(value) => pattern.push_str(&regex::escape(value)),
+                    None => pattern.push_str(".*?"),
+                },
+            }
+        }
+        pattern.push('$');
+        Ok(Regex::new(&pattern)?)
+    }
+
+    fn placeholders(&self) -> String {
+        self.values
+            .keys()
+            .map(|name| format!("{{{}}}", name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+enum Token {
+    Literal(String),
+    Placeholder(String),
+}
+
+fn parse(template: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                match double_braced_name(&chars) {
+                    Some((name, length)) => {
+                        chars.nth(length - 1);
+                        if !literal.is_empty() {
+                            tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                        }
+                        tokens.push(Token::Placeholder(name));
+                    }
+                    None => literal.push('{'),
+                }
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => {
+                            return Err(anyhow::anyhow!(
+                                "Unclosed placeholder in template: {}",
+                                template
+                            ))
+                        }
+                    }
+                }
+                if !literal.is_empty() {
+                    tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                }
+                tokens.push(Token::Placeholder(name.trim().to_string()));
+            }
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+    Ok(tokens)
+}
+
+/// Read a `name}}` placeholder that follows `{{`
+///
+/// Returns the name and the number of characters to skip, or None if the
+/// braces do not surround a plain name.
+fn double_braced_name(chars: &Peekable<Chars>) -> Option<(String, usize)> {
+    let mut chars = chars.clone();
+    let mut name = String::new();
+    let mut length = 0;
+    for c in chars.by_ref() {
+        length += 1;
+        match c {
+            '}' => break,
+            c if c.is_alphanumeric() || c == '_' || c == ' ' => name.push(c),
+            _ => return None,
+        }
+    }
+    let name = name.trim();
+    let valid = !name.is_empty() && !name.contains(' ') && chars.next() == Some('}');
+    valid.then(|| (name.to_string(), length + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let context = TemplateContext::new()
+            .with("prefix", "api-")
+            .with("version", "25.3.0");
+        assert_eq!(context.render("{prefix}{version}").unwrap(), "api-25.3.0");
+        assert_eq!(
+            context.render("release {{{ version }}}").unwrap(),
+            "release {25.3.0}"
+        );
+        assert_eq!(context.render("v{{version}}").unwrap(), "v25.3.0");
+        assert_eq!(
+            context
+                .render("{{ prefix }}{{version}} {{}} {{a-b}}")
+                .unwrap(),
+            "api-25.3.0 {} {a-b}"
+        );
+        assert!(context.render("v{{tag}}").is_err());
+    }
+
+    #[test]
+    fn test_render_unknown_placeholder() {
+        let context = TemplateContext::new().with("version", "25.3.0");
+        let error = context.render("{tag}").unwrap_err().to_string();
+        assert!(error.contains("Unknown placeholder '{tag}'"));
+        assert!(error.contains("available placeholders: {version}"));
+    }
+
+    #[test]
+    fn test_to_regex() {
+        let context = TemplateContext::new()
+            .with("project", "api")
+            .with("version", "25.3.0");
+        let pattern = context
+            .to_regex("release({project}): {version} {tag} [skip ci]")
+            .unwrap();
+        assert!(pattern.is_match("release(api): 25.3.0 api-25.3.0 [skip ci]"));
+        assert!(pattern.is_match("release(api): 25.3.0 multi\nline [skip ci]"));
+        assert!(!pattern.is_match("release(api): 25.3.1 api-25.3.1 [skip ci]"));
+        assert!(!pattern.is_match("release(web): 25.3.0 web-25.3.0 [skip ci]"));
+    }
+
+    #[test]
+    fn test_render_unclosed_placeholder() {
+        let context = TemplateContext::new().with("version", "25.3.0");
+        assert!(context.render("v{version").is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    dependents, projects,
+    settings::{ProjectSettings, Settings},
+};
+
+/// Check the configuration against the files in the repository
+///
+/// Every project manifest must exist and contain a readable version, and
+/// every dependent must be updatable. Nothing is written. Returns all
+/// problems found, each prefixed with the project name.
+pub fn validate(settings: &Settings, repo_path: &Path) -> Vec<String> {
+    let mut names = settings.projects.keys().collect::<Vec<_>>();
+    names.sort();
+    let mut problems = Vec::new();
+    for name in names {
+        let project_settings = &settings.projects[name];
+        if let Err(e) = validate_project(project_settings, repo_path) {
+            problems.push(format!("{}: {:#}", name, e));
+        }
+        for dependent in project_settings.dependents.iter().flatten() {
+            let result = dependents::get_dependent(dependent, repo_path.to_path_buf())
+                .and_then(|dependent| dependent.validate());
+            if let Err(e) = result {
+                problems.push(format!(
+                    "{}: dependent {}: {:#}",
+                    name,
+                    dependent.dependent_path.display(),
+                    e
+                ));
+            }
+        }
+    }
+    problems
+}
+
+fn validate_project(project_settings: &ProjectSettings, repo_path: &Path) -> Result<()> {
+    project_settings.check_command()?;
+    let project_file =
+        projects::get_project_file(project_settings.clone(), repo_path.to_path_buf());
+    let manifest_path = project_file.get_manifest_file_path()?;
+    if !repo_path.join(&manifest_path).is_file() {
+        return Err(anyhow::anyhow!(
+            "Manifest {} does not exist",
+            manifest_path.display()
+        ));
+    }
+    let content = crate::io::read_file(&manifest_path, &repo_path.to_path_buf())?;
+    project_file.version_context(&content).with_context(|| {
+        format!(
+            "Could not read the version from {}",
+            manifest_path.display()
+        )
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::write;
+
+    #[test]
+    fn test_validate() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            "api/Cargo.toml",
+            "[package]\nversion = \"25.1.0\"\n",
+        );
+        write(dir.path(), "deploy/values.yaml", "image:\n  tag: 25.1.0\n");
+        write(dir.path(), "README.md", "Install api 25.1.0\n");
+        write(dir.path(), "charts/api/values.yaml", "");
+        let settings: Settings = serde_yaml::from_str(
+            r#"
+projects:
+  api:
+    type: rust
+    path: api
+    dependents:
+      - type: yaml
+        path: deploy/values.yaml
+        selector: image.tag
+      - type: yaml
+        path: deploy/values.yaml
+        selector: image.version
+      - type: regex
+        path: README.md
+        selector: 'api \d+\.\d+\.\d+'
+      - type: regex
+        path: README.md
+        selector: 'web \d+'
+      - type: regex
+        path: README.md
+        selector: '(unclosed'
+      - type: helm
+        path: charts/api
+        selector: appVersion
+      - type: exec
+        path: README.md
+  plugin:
+    type: exec
+    path: plugin
+    manifest_path: README.md
+  web:
+    type: toml
+    path: web
+    manifest_path: web/pyproject.toml
+    selector: project.version
+"#,
+        )
+        .unwrap();
+        let problems = validate(&settings, dir.path());
+        assert_eq!(problems.len(), 7, "{:#?}", problems);
+        assert!(problems[0].starts_with("api: dependent deploy/values.yaml: "));
+        assert_eq!(
+            problems[1],
+            "api: dependent README.md: Selector 'web \\d+' does not match anything in README.md"
+        );
+        assert!(problems[2].starts_with("api: dependent README.md: regex parse error"));
+        assert_eq!(
+            problems[3],
+            "api: dependent charts/api: Chart directory charts/api has no Chart.yaml"
+        );
+        assert_eq!(
+            problems[4],
+            "api: dependent README.md: Command is required for exec dependent"
+        );
+        assert_eq!(
+            problems[5],
+            "plugin: Command is required for an exec project"
+        );
+        assert_eq!(
+            problems[6],
+            "web: Manifest web/pyproject.toml does not exist"
+        );
+    }
+}
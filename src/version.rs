@@ -1,17 +1,26 @@
 use core::fmt;
+use std::str::FromStr;
 
 use chrono::Datelike;
 
 use crate::edit::LineContext;
 
 /// A version in the format of YY.MM.PATCH
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Version {
     pub major: u32,
     pub minor: u32,
     pub patch: u32,
 }
 
+/// Semantic versioning bump level, ordered from the smallest to the largest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
 #[derive(Debug, Clone)]
 pub struct VersionContext {
     pub version: Version,
@@ -70,6 +79,23 @@ impl Version {
             patch: self.patch + 1,
         }
     }
+
+    /// Bump version according to semantic versioning
+    pub fn bump_semver(&self, bump: Bump) -> Self {
+        match bump {
+            Bump::Patch => self.bump_patch(),
+            Bump::Minor => Self {
+                major: self.major,
+                minor: self.minor + 1,
+                patch: 0,
+            },
+            Bump::Major => Self {
+                major: self.major + 1,
+                minor: 0,
+                patch: 0,
+            },
+        }
+    }
 }
 
 impl VersionContext {
@@ -82,7 +108,7 @@ impl VersionContext {
         }
     }
 
-    pub fn from_line_context(line_context: LineContext) -> Self {
+    pub(crate) fn from_line_context(line_context: LineContext) -> Self {
         let version = line_context.value.to_version();
         let next_version = version.bump();
         Self {
@@ -99,6 +125,29 @@ impl fmt::Display for Version {
     }
 }
 
+impl FromStr for Version {
+    type Err = anyhow::Error;
+
+    /// Parse a version strictly, requiring three numeric parts
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        let parts = version
+            .split('.')
+            .map(|part| part.parse::<u32>().ok().filter(|_| !part.starts_with('+')))
+            .collect::<Option<Vec<_>>>();
+        match parts.as_deref() {
+            Some(&[major, minor, patch]) => Ok(Self {
+                major,
+                minor,
+                patch,
+            }),
+            _ => Err(anyhow::anyhow!(
+                "Invalid version '{}', expected MAJOR.MINOR.PATCH",
+                version
+            )),
+        }
+    }
+}
+
 pub trait ToVersion {
     fn to_version(&self) -> Version;
 }
@@ -114,3 +163,25 @@ impl ToVersion for String {
         Version::parse(self)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bump_semver() {
+        let version = "1.2.3".to_version();
+        assert_eq!(version.bump_semver(Bump::Patch), "1.2.4".to_version());
+        assert_eq!(version.bump_semver(Bump::Minor), "1.3.0".to_version());
+        assert_eq!(version.bump_semver(Bump::Major), "2.0.0".to_version());
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("25.3.7".parse::<Version>().unwrap(), "25.3.7".to_version());
+        assert!("25.3".parse::<Version>().is_err());
+        assert!("25.3.7.1".parse::<Version>().is_err());
+        assert!("v25.3.7".parse::<Version>().is_err());
+        assert!("25.+3.7".parse::<Version>().is_err());
+    }
+}
@@ -0,0 +1,57 @@
+use std::{fs, path::Path, process::Command};
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Hooks receive `MONOVERSE_*` variables that must not be read as settings
+#[test]
+fn test_hook_calls_monoverse() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("VERSION"), "1.0.0").unwrap();
+    fs::write(
+        dir.path().join("monoverse.yaml"),
+        r#"
+projects:
+  app:
+    type: versionfile
+    manifest_path: VERSION
+    scheme: semver
+    hooks:
+      post_update:
+        - '"$MONOVERSE_BIN" validate > validate.txt'
+        - '"$MONOVERSE_BIN" next app > next.txt'
+"#,
+    )
+    .unwrap();
+    git(dir.path(), &["init", "-q"]);
+    git(dir.path(), &["config", "user.name", "Test"]);
+    git(dir.path(), &["config", "user.email", "test@example.com"]);
+    git(dir.path(), &["add", "."]);
+    git(dir.path(), &["commit", "-q", "-m", "feat: initial commit"]);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_monoverse"))
+        .args(["release", "app", "--force", "--commit"])
+        .env("MONOVERSE_BIN", env!("CARGO_BIN_EXE_monoverse"))
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1.0.1\n");
+    assert_eq!(
+        fs::read_to_string(dir.path().join("next.txt")).unwrap(),
+        "1.0.2\n"
+    );
+    let files = git(dir.path(), &["show", "--name-only", "--format=", "HEAD"]);
+    assert_eq!(files, "VERSION\nnext.txt\nvalidate.txt\n");
+}